@@ -0,0 +1,393 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use winit::event::{DeviceEvent, MouseButton, VirtualKeyCode};
+use crate::event::{Event, WindowEvent};
+use crate::input::Buttons;
+use crate::shortcut::{key_name, parse_key};
+
+/// A key or mouse button which can be bound to an action.
+///
+/// In config files it's a string: a key name like in shortcuts (e.g. `"Space"`, `"W"`, `"LShift"`),
+/// or `"Mouse.Left"`, `"Mouse.Right"`, `"Mouse.Middle"` or `"Mouse.<number>"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton)
+}
+
+/// Which direction of [DeviceEvent::MouseMotion] drives an axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseAxis {
+    X,
+    Y
+}
+
+/// Inputs for an axis: held `positive` inputs add 1, held `negative` inputs subtract 1,
+/// and mouse motion adds its delta times `sensitivity`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    #[serde(default)]
+    pub positive: Vec<Input>,
+    #[serde(default)]
+    pub negative: Vec<Input>,
+    #[serde(default)]
+    pub mouse_motion: Option<MouseAxis>,
+    #[serde(default = "default_sensitivity")]
+    pub sensitivity: f64
+}
+
+/// Named actions (e.g. `"jump"`) and axes (e.g. `"move_x"`) and the inputs bound to them.
+///
+/// Load and save as TOML or RON, e.g.
+///
+/// ```toml
+/// [actions]
+/// jump = ["Space", "Mouse.Right"]
+///
+/// [axes.move_x]
+/// positive = ["D", "Right"]
+/// negative = ["A", "Left"]
+///
+/// [axes.look_x]
+/// mouse_motion = "X"
+/// sensitivity = 0.1
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ActionBindings {
+    #[serde(default)]
+    pub actions: BTreeMap<String, Vec<Input>>,
+    #[serde(default)]
+    pub axes: BTreeMap<String, AxisBinding>
+}
+
+/// Action and axis states, folded from the events a proxy receives via [ActionMap::handle_event].
+///
+/// Like [InputState](crate::input::InputState), "pressed" and "released" are edges which last until
+/// [ActionMap::end_frame], and so is mouse motion.
+#[derive(Debug, Clone)]
+pub struct ActionMap {
+    bindings: ActionBindings,
+    inputs: Buttons<Input>,
+    mouse_motion: (f64, f64)
+}
+
+/// An input string isn't a known key or mouse button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownInput(pub String);
+
+/// Loading or saving [ActionBindings] failed
+#[derive(Debug)]
+pub enum ActionConfigError {
+    Io(std::io::Error),
+    /// The file extension isn't `.toml` or `.ron`
+    UnknownFormat(PathBuf),
+    TomlDeserialize(toml::de::Error),
+    TomlSerialize(toml::ser::Error),
+    RonDeserialize(ron::error::SpannedError),
+    RonSerialize(ron::Error)
+}
+
+fn default_sensitivity() -> f64 {
+    1.0
+}
+
+impl FromStr for Input {
+    type Err = UnknownInput;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('.') {
+            Some((device, button)) if device.eq_ignore_ascii_case("mouse") => {
+                let button = match button.to_ascii_lowercase().as_str() {
+                    "left" => MouseButton::Left,
+                    "right" => MouseButton::Right,
+                    "middle" => MouseButton::Middle,
+                    other => MouseButton::Other(other.parse().map_err(|_| UnknownInput(s.to_string()))?)
+                };
+                Ok(Input::Mouse(button))
+            }
+            _ => parse_key(s).map(Input::Key).ok_or_else(|| UnknownInput(s.to_string()))
+        }
+    }
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Key(key) => write!(f, "{}", key_name(*key)),
+            Input::Mouse(MouseButton::Left) => write!(f, "Mouse.Left"),
+            Input::Mouse(MouseButton::Right) => write!(f, "Mouse.Right"),
+            Input::Mouse(MouseButton::Middle) => write!(f, "Mouse.Middle"),
+            Input::Mouse(MouseButton::Other(button)) => write!(f, "Mouse.{}", button)
+        }
+    }
+}
+
+impl TryFrom<String> for Input {
+    type Error = UnknownInput;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Input> for String {
+    fn from(input: Input) -> Self {
+        input.to_string()
+    }
+}
+
+impl AxisBinding {
+    /// Keys or buttons for each direction
+    pub fn digital(positive: Vec<Input>, negative: Vec<Input>) -> Self {
+        AxisBinding { positive, negative, mouse_motion: None, sensitivity: default_sensitivity() }
+    }
+
+    /// Mouse motion in the direction, scaled by `sensitivity`
+    pub fn mouse(axis: MouseAxis, sensitivity: f64) -> Self {
+        AxisBinding { positive: Vec::new(), negative: Vec::new(), mouse_motion: Some(axis), sensitivity }
+    }
+}
+
+impl ActionBindings {
+    pub fn new() -> Self {
+        ActionBindings::default()
+    }
+
+    /// Loads from a `.toml` or `.ron` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ActionConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(ActionConfigError::Io)?;
+        match ConfigFormat::of(path)? {
+            ConfigFormat::Toml => ActionBindings::from_toml(&text),
+            ConfigFormat::Ron => ActionBindings::from_ron(&text)
+        }
+    }
+
+    /// Saves to a `.toml` or `.ron` file, e.g. after the user rebinds inputs
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ActionConfigError> {
+        let path = path.as_ref();
+        let text = match ConfigFormat::of(path)? {
+            ConfigFormat::Toml => self.to_toml()?,
+            ConfigFormat::Ron => self.to_ron()?
+        };
+        std::fs::write(path, text).map_err(ActionConfigError::Io)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ActionConfigError> {
+        toml::from_str(text).map_err(ActionConfigError::TomlDeserialize)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ActionConfigError> {
+        toml::to_string_pretty(self).map_err(ActionConfigError::TomlSerialize)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, ActionConfigError> {
+        ron::from_str(text).map_err(ActionConfigError::RonDeserialize)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ActionConfigError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(ActionConfigError::RonSerialize)
+    }
+
+    /// Adds an input to the action, creating it if necessary
+    pub fn bind(&mut self, action: impl Into<String>, input: Input) {
+        let inputs = self.actions.entry(action.into()).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    /// Removes an input from the action
+    pub fn unbind(&mut self, action: &str, input: Input) {
+        if let Some(inputs) = self.actions.get_mut(action) {
+            inputs.retain(|bound_input| *bound_input != input);
+        }
+    }
+
+    /// Replaces the inputs of the action
+    pub fn rebind(&mut self, action: impl Into<String>, inputs: Vec<Input>) {
+        self.actions.insert(action.into(), inputs);
+    }
+
+    /// Replaces the binding of the axis
+    pub fn bind_axis(&mut self, axis: impl Into<String>, binding: AxisBinding) {
+        self.axes.insert(axis.into(), binding);
+    }
+
+    /// Actions the input is bound to, e.g. to find conflicts when rebinding
+    pub fn actions_bound_to(&self, input: Input) -> impl Iterator<Item=&str> + '_ {
+        self.actions.iter().filter(move |(_, inputs)| inputs.contains(&input)).map(|(action, _)| action.as_str())
+    }
+}
+
+impl ActionMap {
+    pub fn new(bindings: ActionBindings) -> Self {
+        ActionMap {
+            bindings,
+            inputs: Buttons::default(),
+            mouse_motion: (0.0, 0.0)
+        }
+    }
+
+    pub fn bindings(&self) -> &ActionBindings {
+        &self.bindings
+    }
+
+    /// Rebind at runtime. Takes effect immediately, inputs which are already held stay held
+    pub fn bindings_mut(&mut self) -> &mut ActionBindings {
+        &mut self.bindings
+    }
+
+    /// Updates input states from a key, mouse button or mouse motion event, from any window or device
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput { input, .. } => if let Some(key) = input.virtual_keycode {
+                    self.inputs.set(Input::Key(key), input.state);
+                },
+                WindowEvent::MouseInput { state, button, .. } => self.inputs.set(Input::Mouse(*button), *state),
+                // Losing focus swallows the key-ups
+                WindowEvent::Focused(false) => self.inputs.release_all(),
+                _ => {}
+            },
+            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (x, y) }, .. } => {
+                self.mouse_motion.0 += x;
+                self.mouse_motion.1 += y;
+            }
+            _ => {}
+        }
+    }
+
+    /// Resets "pressed" and "released" edges and mouse motion, starting the next frame.
+    pub fn end_frame(&mut self) {
+        self.inputs.end_frame();
+        self.mouse_motion = (0.0, 0.0);
+    }
+
+    /// Whether any input bound to the action is held
+    pub fn is_held(&self, action: &str) -> bool {
+        self.inputs_of(action).any(|input| self.inputs.held.contains(input))
+    }
+
+    /// Whether any input bound to the action was pressed this frame
+    pub fn was_pressed(&self, action: &str) -> bool {
+        self.inputs_of(action).any(|input| self.inputs.pressed.contains(input))
+    }
+
+    /// Whether an input bound to the action was released this frame and none are held
+    pub fn was_released(&self, action: &str) -> bool {
+        !self.is_held(action) && self.inputs_of(action).any(|input| self.inputs.released.contains(input))
+    }
+
+    /// The axis value this frame: -1 to 1 from held inputs, plus scaled mouse motion. 0 if the axis isn't bound
+    pub fn axis(&self, axis: &str) -> f64 {
+        let binding = match self.bindings.axes.get(axis) {
+            None => return 0.0,
+            Some(binding) => binding
+        };
+        let is_held = |inputs: &[Input]| inputs.iter().any(|input| self.inputs.held.contains(input));
+        let mut value = match (is_held(&binding.positive), is_held(&binding.negative)) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0
+        };
+        match binding.mouse_motion {
+            None => {}
+            Some(MouseAxis::X) => value += self.mouse_motion.0 * binding.sensitivity,
+            Some(MouseAxis::Y) => value += self.mouse_motion.1 * binding.sensitivity
+        }
+        value
+    }
+
+    fn inputs_of<'a>(&'a self, action: &str) -> impl Iterator<Item=&'a Input> + 'a {
+        self.bindings.actions.get(action).into_iter().flatten()
+    }
+}
+
+enum ConfigFormat {
+    Toml,
+    Ron
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Result<Self, ActionConfigError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("ron") => Ok(ConfigFormat::Ron),
+            _ => Err(ActionConfigError::UnknownFormat(path.to_path_buf()))
+        }
+    }
+}
+
+impl Display for UnknownInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown key or mouse button {:?}", self.0)
+    }
+}
+
+impl Error for UnknownInput {}
+
+impl Display for ActionConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionConfigError::Io(error) => write!(f, "couldn't access action config: {}", error),
+            ActionConfigError::UnknownFormat(path) => write!(f, "action config {} isn't .toml or .ron", path.display()),
+            ActionConfigError::TomlDeserialize(error) => write!(f, "invalid action config: {}", error),
+            ActionConfigError::TomlSerialize(error) => write!(f, "couldn't serialize action config: {}", error),
+            ActionConfigError::RonDeserialize(error) => write!(f, "invalid action config: {}", error),
+            ActionConfigError::RonSerialize(error) => write!(f, "couldn't serialize action config: {}", error)
+        }
+    }
+}
+
+impl Error for ActionConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ActionConfigError::Io(error) => Some(error),
+            ActionConfigError::UnknownFormat(_) => None,
+            ActionConfigError::TomlDeserialize(error) => Some(error),
+            ActionConfigError::TomlSerialize(error) => Some(error),
+            ActionConfigError::RonDeserialize(error) => Some(error),
+            ActionConfigError::RonSerialize(error) => Some(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{MouseButton, VirtualKeyCode};
+    use crate::shortcut::KEY_CODES;
+    use super::{ActionBindings, AxisBinding, Input, MouseAxis};
+
+    fn every_input() -> ActionBindings {
+        let mut bindings = ActionBindings::new();
+        for (_, key) in KEY_CODES {
+            bindings.bind("key", Input::Key(*key));
+        }
+        for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::Other(4)] {
+            bindings.bind("mouse", Input::Mouse(button));
+        }
+        bindings.bind_axis("move_x", AxisBinding::digital(vec![Input::Key(VirtualKeyCode::D)], vec![Input::Key(VirtualKeyCode::A)]));
+        bindings.bind_axis("look_x", AxisBinding::mouse(MouseAxis::X, 0.1));
+        bindings
+    }
+
+    #[test]
+    fn toml_round_trips_every_input() {
+        let bindings = every_input();
+        let text = bindings.to_toml().unwrap();
+        assert_eq!(ActionBindings::from_toml(&text).unwrap(), bindings);
+    }
+
+    #[test]
+    fn ron_round_trips_every_input() {
+        let bindings = every_input();
+        let text = bindings.to_ron().unwrap();
+        assert_eq!(ActionBindings::from_ron(&text).unwrap(), bindings);
+    }
+}
@@ -9,7 +9,10 @@
 //! Different proxies may have different custom event implementations. As a result we decided to
 //! make 2 kinds of custom events: `Box<dyn Any>`, and if you care about allocation, `usize`,
 //! can be sent as custom events. Custom events are one of the ways to communicate across [ProxyEventLoop]s.
-use std::any::Any;
+//!
+//! [UserEvent](crate::event::UserEvent) is the untyped transport. A proxy created with [EventLoop::with_user_event](crate::event_loop::EventLoop::with_user_event) only
+//! receives the user events which downcast to its type, and receives them already downcast.
+use std::any::{Any, TypeId};
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::path::PathBuf;
@@ -20,14 +23,23 @@ use winit::event::{AxisId, DeviceEvent, DeviceId, ElementState, KeyboardInput, M
 use winit::window::{Theme, WindowId};
 
 /// Event which gets sent to proxy [EventLoop]s. See [winit::event::Event] for details.
+///
+/// `T` is the type of user events. Proxies created with [EventLoop::new](crate::event_loop::EventLoop::new)
+/// receive every [UserEvent], proxies created with [EventLoop::with_user_event](crate::event_loop::EventLoop::with_user_event)
+/// only receive the ones which downcast to `T`.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Event {
+pub enum Event<T = UserEvent> {
     /// Emitted when new events arrive from the OS to be processed.
     ///
     /// This event type is useful as a place to put code that should be done before you start
     /// processing events, such as updating frame timing information for benchmarking or checking
     /// the [`StartCause`][crate::event::StartCause] to see if a timer set by
     /// [`ControlFlow::WaitUntil`](crate::event_loop::ControlFlow::WaitUntil) has elapsed.
+    ///
+    /// The [`StartCause`] is specific to each proxy: it describes the proxy's own [`ControlFlow`](crate::event_loop::ControlFlow)
+    /// and timer, not the main loop's.
+    ///
+    /// Proxies registered after the main loop started receive `NewEvents(StartCause::Init)` before any other event.
     NewEvents(StartCause),
 
     /// Emitted when the OS sends an event to a winit window.
@@ -42,13 +54,19 @@ pub enum Event {
         event: DeviceEvent,
     },
 
-    /// Emitted when an event is sent from [`EventLoopProxy::send_event`](crate::event_loop::EventLoopProxy::send_event)
-    UserEvent(UserEvent),
+    /// Emitted when an event is sent from [`EventLoopProxy::send_event`](crate::event_loop::EventLoopProxy::send_event),
+    /// or sent to this proxy via [`EventLoopProxy::send_event_to`](crate::event_loop::EventLoopProxy::send_event_to)
+    /// or [`EventLoopProxy::publish`](crate::event_loop::EventLoopProxy::publish)
+    UserEvent(T),
 
     /// Emitted when the application has been suspended.
+    ///
+    /// Proxies registered later receive the latest of this and [`Event::Resumed`] when they register.
     Suspended,
 
     /// Emitted when the application has been resumed.
+    ///
+    /// Proxies registered later receive the latest of this and [`Event::Suspended`] when they register.
     Resumed,
 
     /// Emitted when all of the event loop's input events have been processed and redraw processing
@@ -70,6 +88,10 @@ pub enum Event {
     ///   resizing the window).
     /// - The application has explicitly requested a redraw via
     ///   [`Window::request_redraw`](crate::window::Window::request_redraw).
+    /// - A proxy scheduled a redraw via [`EventLoop::schedule_redraw`](crate::event_loop::EventLoop::schedule_redraw),
+    ///   and the redraw rate allows it, so the main loop requested it.
+    ///
+    /// In every case [`EventLoop::frame_clock`](crate::event_loop::EventLoop::frame_clock) has this frame's timing.
     ///
     /// During each iteration of the event loop, Winit will aggregate duplicate redraw requests
     /// into a single event, to help avoid duplicating rendering work.
@@ -277,6 +299,9 @@ pub enum UserEvent {
 /// If you don't need this you can create a dummy implementation which always returns `false`.
 /// They must implement cloning and [Send] because they will get cloned and sent to each proxy.
 /// They must implement [Any] so they can be downcasted.
+///
+/// This is implemented for every `Clone + PartialEq + Debug + Send + 'static` type,
+/// so you only need to implement it yourself if your event type isn't [Clone] or [PartialEq].
 pub trait UserEventTrait: Any + Debug + Send {
     /// Custom events must support testing for equality because [Event] is.
     /// If you don't need this you can create a dummy implementation which always returns `false`.
@@ -284,6 +309,19 @@ pub trait UserEventTrait: Any + Debug + Send {
     fn clone(&self) -> Box<dyn UserEventTrait>;
 }
 
+impl<T: Any + Clone + PartialEq + Debug + Send> UserEventTrait for T {
+    fn rough_eq(&self, other: &dyn UserEventTrait) -> bool {
+        match (other as &dyn Any).downcast_ref::<T>() {
+            None => false,
+            Some(other) => self == other
+        }
+    }
+
+    fn clone(&self) -> Box<dyn UserEventTrait> {
+        Box::new(Clone::clone(self))
+    }
+}
+
 impl Clone for Box<dyn UserEventTrait> {
     fn clone(&self) -> Self {
         UserEventTrait::clone(self.as_ref())
@@ -306,21 +344,80 @@ impl PartialEq for UserEvent {
     }
 }
 
+impl UserEvent {
+    /// Wraps a custom event. `usize`s become [UserEvent::Primitive], [UserEvent]s are returned as-is,
+    /// and everything else is boxed.
+    pub fn new<T: UserEventTrait>(value: T) -> UserEvent {
+        let value: Box<dyn UserEventTrait> = Box::new(value);
+        let value_any = value.as_ref() as &dyn Any;
+        if let Some(value) = value_any.downcast_ref::<usize>() {
+            UserEvent::Primitive(*value)
+        } else if value_any.is::<UserEvent>() {
+            let value: Box<dyn Any> = value;
+            *value.downcast::<UserEvent>().unwrap()
+        } else {
+            UserEvent::Box(value)
+        }
+    }
+
+    /// Returns a reference to the custom event if it's a `T`.
+    pub fn downcast_ref<T: UserEventTrait>(&self) -> Option<&T> {
+        if let Some(this) = (self as &dyn Any).downcast_ref::<T>() {
+            return Some(this);
+        }
+        match self {
+            UserEvent::Primitive(value) => (value as &dyn Any).downcast_ref::<T>(),
+            UserEvent::Box(value) => (value.as_ref() as &dyn Any).downcast_ref::<T>()
+        }
+    }
+
+    /// The type of the custom event, which [UserEvent::downcast] succeeds for
+    pub(crate) fn value_type_id(&self) -> TypeId {
+        match self {
+            UserEvent::Primitive(_) => TypeId::of::<usize>(),
+            UserEvent::Box(value) => (value.as_ref() as &dyn Any).type_id()
+        }
+    }
+
+    /// Downcasts into the custom event, or returns the event back if it's not a `T`.
+    /// If `T` is [UserEvent] this always succeeds.
+    pub fn downcast<T: UserEventTrait>(self) -> Result<T, UserEvent> {
+        if TypeId::of::<T>() == TypeId::of::<UserEvent>() {
+            let this: Box<dyn Any> = Box::new(self);
+            return Ok(*this.downcast::<T>().unwrap());
+        }
+        match self {
+            UserEvent::Primitive(value) => {
+                let value: Box<dyn Any> = Box::new(value);
+                value.downcast::<T>().map(|value| *value).map_err(|value| UserEvent::Primitive(*value.downcast::<usize>().unwrap()))
+            }
+            UserEvent::Box(value) => {
+                if (value.as_ref() as &dyn Any).is::<T>() {
+                    let value: Box<dyn Any> = value;
+                    Ok(*value.downcast::<T>().unwrap())
+                } else {
+                    Err(UserEvent::Box(value))
+                }
+            }
+        }
+    }
+}
+
 #[allow(deprecated)]
 impl Event {
     pub fn from(event: winit::event::Event<'_, UserEvent>) -> (Self, Option<&mut PhysicalSize<u32>>) {
         match event {
-            winit::event::Event::NewEvents(x) => (Event::NewEvents(x.clone()), None),
+            winit::event::Event::NewEvents(x) => (Event::NewEvents(x), None),
             winit::event::Event::WindowEvent { window_id, event } => {
                 let (event, physical_size) = WindowEvent::from(event);
-                (Event::WindowEvent { window_id: window_id.clone(), event }, physical_size)
+                (Event::WindowEvent { window_id, event }, physical_size)
             },
-            winit::event::Event::DeviceEvent { device_id, event } => (Event::DeviceEvent { device_id: device_id.clone(), event: event.clone() }, None),
-            winit::event::Event::UserEvent(x) => (Event::UserEvent(x.clone()), None),
+            winit::event::Event::DeviceEvent { device_id, event } => (Event::DeviceEvent { device_id, event }, None),
+            winit::event::Event::UserEvent(x) => (Event::UserEvent(x), None),
             winit::event::Event::Suspended => (Event::Suspended, None),
             winit::event::Event::Resumed => (Event::Resumed, None),
             winit::event::Event::MainEventsCleared => (Event::MainEventsCleared, None),
-            winit::event::Event::RedrawRequested(x) => (Event::RedrawRequested(x.clone()), None),
+            winit::event::Event::RedrawRequested(x) => (Event::RedrawRequested(x), None),
             winit::event::Event::RedrawEventsCleared => (Event::RedrawEventsCleared, None),
             winit::event::Event::LoopDestroyed => (Event::LoopDestroyed, None)
         }
@@ -345,6 +442,25 @@ impl Event {
     }
 }
 
+impl<T> Event<T> {
+    /// Converts the user event if there is one, otherwise returns the same event with a different user event type.
+    /// If `convert` fails the original event is returned.
+    pub fn try_map_user_event<U>(self, convert: impl FnOnce(T) -> Result<U, T>) -> Result<Event<U>, Event<T>> {
+        match self {
+            Event::NewEvents(x) => Ok(Event::NewEvents(x)),
+            Event::WindowEvent { window_id, event } => Ok(Event::WindowEvent { window_id, event }),
+            Event::DeviceEvent { device_id, event } => Ok(Event::DeviceEvent { device_id, event }),
+            Event::UserEvent(x) => convert(x).map(Event::UserEvent).map_err(Event::UserEvent),
+            Event::Suspended => Ok(Event::Suspended),
+            Event::Resumed => Ok(Event::Resumed),
+            Event::MainEventsCleared => Ok(Event::MainEventsCleared),
+            Event::RedrawRequested(x) => Ok(Event::RedrawRequested(x)),
+            Event::RedrawEventsCleared => Ok(Event::RedrawEventsCleared),
+            Event::LoopDestroyed => Ok(Event::LoopDestroyed)
+        }
+    }
+}
+
 #[allow(deprecated)]
 impl WindowEvent {
     pub fn from(event: winit::event::WindowEvent<'_>) -> (Self, Option<&mut PhysicalSize<u32>>) {
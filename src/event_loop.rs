@@ -1,18 +1,29 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::cmp::Ordering;
+use std::marker::PhantomData;
 use std::sync::Arc;
 use crossbeam_utils::atomic::AtomicCell;
 use flume::{Receiver, Sender, TryRecvError, TrySendError};
 use std::cell::{Cell, RefCell};
-use std::collections::VecDeque;
-use winit::window::{Window, WindowBuilder};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Pixel};
+use winit::window::{Window, WindowBuilder, WindowId};
 use winit::error::OsError;
+use winit::event_loop::EventLoopClosed;
 use futures::executor::block_on;
 use std::task::Waker;
-use std::time::Instant;
-use crate::event::Event;
+use std::time::{Duration, Instant};
+use crate::event::{Event, UserEvent, UserEventTrait, WindowEvent};
+use crate::input::InputState;
+use crate::frame_stats::{FrameStats, FrameStatsCollector, FrameStatsConfig};
 use crate::future::{FutResponse, PendingRequest, FutEventLoop};
-use crate::messages::{ProxyRegister, ProxyRegisterBody, ProxyRegisterInfo, ProxyRequest, ProxyResponse, REGISTER_PROXY};
+use crate::redraw::FrameClock;
+use crate::shortcut::{Shortcut, ShortcutConflict, ShortcutMatcher, ShortcutScope};
+use crate::timer::{self, Interval, Sleep};
+use crate::window_state::{WindowSnapshot, WindowState, WindowStates};
+use crate::messages::{main_proxy, MainEvent, ProxyRegister, ProxyRegisterBody, ProxyRegisterInfo, ProxyRequest, ProxyResponse, REGISTER_PROXY, Route};
 
 /// A proxy event loop.
 ///
@@ -24,19 +35,39 @@ use crate::messages::{ProxyRegister, ProxyRegisterBody, ProxyRegisterInfo, Proxy
 ///
 /// The "actual" single event loop must be created via [winit_modular::run].
 /// This forwards all of its messages to the event loop using channels and returns the responses.
-pub struct EventLoop {
-    // id: ProxyId,
+///
+/// Like [winit::event_loop::EventLoop], `T` is the type of user events. [EventLoop::new] creates a
+/// proxy which receives all [UserEvent]s, [EventLoop::with_user_event] creates a proxy which only
+/// receives user events of type `T`, already downcast.
+pub struct EventLoop<T: 'static = UserEvent> {
+    id: ProxyId,
     control_flow: Arc<AtomicCell<ControlFlow>>,
+    timer: Arc<AtomicCell<Option<Instant>>>,
+    main_proxy: winit::event_loop::EventLoopProxy<MainEvent>,
     send: Sender<ProxyRequest>,
     recv: Receiver<ProxyResponse>,
     pending_requests: RefCell<VecDeque<PendingRequest>>,
-    locally_pending_events: RefCell<Vec<Event>>,
-    is_receiving_events: Cell<bool>
+    pending_calls: RefCell<HashMap<u64, PendingRequest>>,
+    next_call_id: Cell<u64>,
+    request_handlers: RefCell<HashMap<TypeId, RequestHandler>>,
+    frame_clocks: RefCell<HashMap<WindowId, FrameClock>>,
+    frame_stats: RefCell<Option<FrameStatsCollector>>,
+    window_states: RefCell<Option<WindowStates>>,
+    input: RefCell<Option<InputState>>,
+    shortcuts: RefCell<ShortcutMatcher>,
+    locally_pending_events: RefCell<Vec<(Event, EventMeta)>>,
+    is_receiving_events: Cell<bool>,
+    _user_event: PhantomData<fn() -> T>
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[doc(hidden)]
+type RequestHandler = Box<dyn FnMut(Box<dyn Any + Send>) -> Box<dyn Any + Send>>;
+
+/// Identifies a proxy [EventLoop], so you can send user events to it specifically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProxyId(pub(crate) u64);
+
 /// Whether an event is during or before the call to [EventLoop::run] or [EventLoop::run_async]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EventIs {
     /// Event was before the call to `run...`
     Buffered,
@@ -44,16 +75,138 @@ pub enum EventIs {
     New
 }
 
+/// Where an event came from and when, passed along with each event to [EventLoop::run], [EventLoop::run_async]
+/// and [EventLoop::run_immediate].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventMeta {
+    /// Increases with every event the main loop sends, and is the same for every proxy receiving the same event,
+    /// so you can order events across proxies
+    pub seq: u64,
+    /// When the main loop received the event
+    pub received: Instant,
+    /// When the main loop sent the event to this proxy. `dispatched - received` is the time spent in the main loop,
+    /// and `Instant::now() - dispatched` in the handler is the latency to this proxy
+    pub dispatched: Instant,
+    /// Which iteration of the main loop the event is in. Iterations start with [Event::NewEvents], the one with
+    /// [StartCause::Init](winit::event::StartCause::Init) is 0
+    pub iteration: u64,
+    /// Whether the event is buffered or new
+    pub is: EventIs,
+    /// The window's current scale factor, for [Event::WindowEvent] and [Event::RedrawRequested].
+    /// The main loop tracks it from window creation and [WindowEvent::ScaleFactorChanged] (which has the new one).
+    /// `None` for other events, and for windows the main loop didn't create (e.g. via [EventLoop::on_main_thread])
+    /// until their scale factor changes
+    pub scale_factor: Option<f64>
+}
+
+impl EventMeta {
+    /// Converts a physical position, e.g. from [WindowEvent::CursorMoved], [WindowEvent::Moved] or
+    /// [WindowEvent::Touch], into logical units using [EventMeta::scale_factor]
+    pub fn to_logical_position<P: Pixel>(&self, position: PhysicalPosition<P>) -> Option<LogicalPosition<f64>> {
+        self.scale_factor.map(|scale_factor| position.to_logical(scale_factor))
+    }
+
+    /// Converts a physical size, e.g. from [WindowEvent::Resized], into logical units using [EventMeta::scale_factor]
+    pub fn to_logical_size<P: Pixel>(&self, size: PhysicalSize<P>) -> Option<LogicalSize<f64>> {
+        self.scale_factor.map(|scale_factor| size.to_logical(scale_factor))
+    }
+}
+
 impl EventLoop {
-    /// Creates a new proxy event loop. However it must first be registered, so this is async.
+    /// Creates a new proxy event loop which receives every [UserEvent].
+    /// However it must first be registered, so this is async.
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> FutEventLoop {
+        EventLoop::with_user_event()
+    }
+
+    /// Registers a keyboard shortcut (parse one from a string, e.g. `"Ctrl+K Ctrl+S".parse()`). When it's pressed
+    /// in the scope, this proxy receives a [ShortcutTriggered](crate::shortcut::ShortcutTriggered) user event with `name`,
+    /// right after the key event which completed it. Replaces the shortcut previously registered with `name`, if any.
+    ///
+    /// Fails if the shortcut conflicts with one already registered by any proxy (see [ShortcutConflict]).
+    /// Then the shortcut previously registered with `name` is kept.
+    ///
+    /// Only proxies which receive every user event (see [EventLoop::new]) can register shortcuts,
+    /// because typed proxies would discard the [ShortcutTriggered](crate::shortcut::ShortcutTriggered) events.
+    pub async fn register_shortcut(&self, name: impl Into<String>, shortcut: Shortcut, scope: ShortcutScope) -> Result<(), ShortcutConflict> {
+        let name = name.into();
+        // Unregister first so the new shortcut doesn't conflict with the one it replaces
+        let previous = self.shortcuts.borrow_mut().unbind(&name);
+        if let Some((previous_shortcut, previous_scope)) = &previous {
+            self.send_without_response(ProxyRequest::UnregisterShortcut { shortcut: Clone::clone(previous_shortcut), scope: *previous_scope });
+        }
+        match self.request_shortcut(Clone::clone(&shortcut), scope).await {
+            Ok(()) => {
+                self.shortcuts.borrow_mut().bind(name, shortcut, scope);
+                Ok(())
+            }
+            Err(conflict) => {
+                if let Some((previous_shortcut, previous_scope)) = previous {
+                    // Only fails if another proxy took it in the meantime
+                    if self.request_shortcut(Clone::clone(&previous_shortcut), previous_scope).await.is_ok() {
+                        self.shortcuts.borrow_mut().bind(name, previous_shortcut, previous_scope);
+                    }
+                }
+                Err(conflict)
+            }
+        }
+    }
+
+    /// Unregisters the shortcut registered with `name`, if any.
+    pub fn unregister_shortcut(&self, name: &str) {
+        let unbound = self.shortcuts.borrow_mut().unbind(name);
+        if let Some((shortcut, scope)) = unbound {
+            self.send_without_response(ProxyRequest::UnregisterShortcut { shortcut, scope });
+        }
+    }
+
+    /// Sets how long to wait for the next stroke of a chord (e.g. `S` after `Ctrl+K` in `Ctrl+K S`)
+    /// before starting over. Defaults to 1 second.
+    pub fn set_chord_timeout(&self, chord_timeout: Duration) {
+        self.shortcuts.borrow_mut().set_chord_timeout(chord_timeout);
+    }
+
+    /// Starts collecting [FrameStats] for every window this proxy handles [Event::RedrawRequested] for,
+    /// timing the event handler from when the main loop received the redraw and from when it was scheduled. Query them with [EventLoop::frame_stats], and if
+    /// [FrameStatsConfig::report_interval] is set, this proxy periodically receives a
+    /// [FrameStatsReport](crate::frame_stats::FrameStatsReport) user event for each window.
+    ///
+    /// Replaces and resets the previous stats, if any. Like shortcuts, only proxies which receive every user event
+    /// (see [EventLoop::new]) can collect stats, because typed proxies would discard the reports.
+    pub fn enable_frame_stats(&self, config: FrameStatsConfig) {
+        *self.frame_stats.borrow_mut() = Some(FrameStatsCollector::new(config));
+    }
+
+    /// Stops collecting frame stats and discards them.
+    pub fn disable_frame_stats(&self) {
+        *self.frame_stats.borrow_mut() = None;
+    }
+
+    /// Stats for the window's latest frames, or `None` if stats aren't enabled
+    /// (see [EventLoop::enable_frame_stats]) or the window wasn't redrawn since.
+    pub fn frame_stats(&self, window_id: WindowId) -> Option<FrameStats> {
+        self.frame_stats.borrow().as_ref().and_then(|frame_stats| frame_stats.stats(window_id))
+    }
+}
+
+impl<T: UserEventTrait> EventLoop<T> {
+    /// Creates a new proxy event loop which only receives user events of type `T`.
+    /// However it must first be registered, so this is async.
+    pub fn with_user_event() -> FutEventLoop<T> {
+        // Not Send because responses may contain non-Send return values, but only this proxy reads them
+        #[allow(clippy::arc_with_non_send_sync)]
         let register_handle = Arc::new(AtomicCell::new(ProxyRegisterBody::Init));
 
         // SAFETY: This is already initialized and will only be read
         let sent = unsafe {
-            REGISTER_PROXY.as_ref()
+            (*std::ptr::addr_of!(REGISTER_PROXY)).as_ref()
                 .expect("you must call winit_modular::run before creating proxy event loops")
-                .try_send(ProxyRegister(Arc::downgrade(&register_handle)))
+                .try_send(ProxyRegister {
+                    body: Arc::downgrade(&register_handle),
+                    // Proxies which accept every user event downcast them to `UserEvent`
+                    user_event: (TypeId::of::<T>() != TypeId::of::<UserEvent>()).then(TypeId::of::<T>)
+                })
         };
         match sent {
             Ok(()) => (),
@@ -62,22 +215,57 @@ impl EventLoop {
         }
 
         FutEventLoop {
-            body: register_handle
+            body: register_handle,
+            snapshot: false,
+            _user_event: PhantomData
         }
     }
 
     pub(crate) fn from(info: ProxyRegisterInfo) -> Self {
         EventLoop {
-            // id: info.id,
+            id: info.id,
             control_flow: info.control_flow,
+            timer: info.timer,
+            main_proxy: main_proxy(),
             send: info.send,
             recv: info.recv,
             pending_requests: RefCell::new(VecDeque::new()),
+            pending_calls: RefCell::new(HashMap::new()),
+            next_call_id: Cell::new(0),
+            request_handlers: RefCell::new(HashMap::new()),
+            frame_clocks: RefCell::new(HashMap::new()),
+            frame_stats: RefCell::new(None),
+            window_states: RefCell::new(None),
+            input: RefCell::new(None),
+            shortcuts: RefCell::new(ShortcutMatcher::new()),
             locally_pending_events: RefCell::new(Vec::new()),
-            is_receiving_events: Cell::new(false)
+            is_receiving_events: Cell::new(false),
+            _user_event: PhantomData
         }
     }
 
+    /// Creates an [EventLoopProxy] which sends user events of type `T` to every proxy event loop.
+    pub fn create_proxy(&self) -> EventLoopProxy<T> {
+        EventLoopProxy::new()
+    }
+
+    /// Identifies this proxy. Pass it to [EventLoopProxy::send_event_to] to send user events only to this proxy.
+    pub fn id(&self) -> ProxyId {
+        self.id
+    }
+
+    /// Receive user events published to `topic` via [EventLoopProxy::publish].
+    ///
+    /// Takes effect before the main loop sends the next event.
+    pub fn subscribe(&self, topic: impl Into<String>) {
+        self.send_without_response(ProxyRequest::Subscribe { topic: topic.into() })
+    }
+
+    /// Stop receiving user events published to `topic`.
+    pub fn unsubscribe(&self, topic: impl Into<String>) {
+        self.send_without_response(ProxyRequest::Unsubscribe { topic: topic.into() })
+    }
+
     /// Runs an arbitrary closure on the main / UI thread.
     ///
     /// Note that the closure must be `'static`, which means it can't reference local variables.
@@ -91,7 +279,7 @@ impl EventLoop {
     /// the closure and then return them along with your "real" result.
     ///
     /// In the future, we may provide more methods to work around this limitation.
-    pub fn on_main_thread<R: Any + Send>(&self, action: impl FnOnce() -> R + Send + 'static) -> FutResponse<'_, R> {
+    pub fn on_main_thread<R: Any + Send>(&self, action: impl FnOnce() -> R + Send + 'static) -> FutResponse<'_, R, T> {
         self.send(ProxyRequest::RunOnMainThread {
             action: Box::new(move || Box::new(action()))
         }, |response| {
@@ -104,7 +292,7 @@ impl EventLoop {
         })
     }
     /// Creates a new [Window], using the function to add arguments
-    pub fn create_window(&self, configure: impl FnOnce(WindowBuilder) -> WindowBuilder + Send + 'static) -> FutResponse<'_, Result<Window, OsError>> {
+    pub fn create_window(&self, configure: impl FnOnce(WindowBuilder) -> WindowBuilder + Send + 'static) -> FutResponse<'_, Result<Window, OsError>, T> {
         self.send(ProxyRequest::SpawnWindow {
             configure: Box::new(configure)
         }, |response| {
@@ -115,26 +303,183 @@ impl EventLoop {
         })
     }
 
+    /// Gets the state of every window from the main loop, see [WindowSnapshot].
+    ///
+    /// It reflects the events the main loop sent before answering, which may include events this proxy hasn't handled yet.
+    /// For a snapshot in order with the events, see [FutEventLoop::with_snapshot].
+    pub fn snapshot(&self) -> FutResponse<'_, WindowSnapshot, T> {
+        self.send(ProxyRequest::Snapshot, |response| {
+            match response {
+                ProxyResponse::Snapshot { snapshot } => snapshot,
+                _ => panic!("incorrect response type, responses were received out-of-order")
+            }
+        })
+    }
+
+    /// Starts maintaining [WindowStates] from the events this proxy handles, before they're passed to the event handler,
+    /// so [EventLoop::window_state] is up to date inside it. [WindowSnapshot] user events are applied too,
+    /// so combine with [FutEventLoop::with_snapshot] to know windows created before this proxy.
+    ///
+    /// Starts from `initial`, e.g. [WindowStates::new] or a [WindowSnapshot] converted via `into`.
+    pub fn track_window_states(&self, initial: WindowStates) {
+        *self.window_states.borrow_mut() = Some(initial);
+    }
+
+    /// Stops maintaining [WindowStates] and discards them.
+    pub fn stop_tracking_window_states(&self) {
+        *self.window_states.borrow_mut() = None;
+    }
+
+    /// The window's state, or `None` if window states aren't tracked (see [EventLoop::track_window_states])
+    /// or the window is unknown.
+    pub fn window_state(&self, window_id: WindowId) -> Option<WindowState> {
+        self.window_states.borrow().as_ref().and_then(|window_states| window_states.get(window_id).copied())
+    }
+
+    /// A copy of every tracked window's state, or `None` if window states aren't tracked (see [EventLoop::track_window_states]).
+    pub fn window_states(&self) -> Option<WindowStates> {
+        self.window_states.borrow().as_ref().cloned()
+    }
+
+    /// Starts maintaining an [InputState] from the events this proxy handles, before they're passed to the event handler.
+    /// Its "pressed this frame" edges reset after the handler handles [Event::MainEventsCleared].
+    ///
+    /// Query it inside the handler with [EventLoop::input].
+    pub fn track_input(&self) {
+        *self.input.borrow_mut() = Some(InputState::new());
+    }
+
+    /// Stops maintaining the [InputState] and discards it.
+    pub fn stop_tracking_input(&self) {
+        *self.input.borrow_mut() = None;
+    }
+
+    /// Queries the tracked [InputState], or returns `None` if input isn't tracked (see [EventLoop::track_input]).
+    pub fn input<R>(&self, query: impl FnOnce(&InputState) -> R) -> Option<R> {
+        self.input.borrow().as_ref().map(query)
+    }
+
+    /// Asks the main loop to send this proxy a [WindowSnapshot] user event
+    pub(crate) fn request_snapshot_event(&self) {
+        self.send_without_response(ProxyRequest::SnapshotEvent)
+    }
+
+    /// Wakes up the main loop at `deadline` and sends this proxy
+    /// [Event::NewEvents]`(`[StartCause::ResumeTimeReached](winit::event::StartCause::ResumeTimeReached)`)` with `requested_resume` set to `deadline`.
+    ///
+    /// Unlike [ControlFlow::WaitUntil], the timer fires once, and the [StartCause](winit::event::StartCause) is correct even
+    /// if other proxies' events wake up the main loop earlier. Replaces the previous timer.
+    pub fn set_timer(&self, deadline: Instant) {
+        self.timer.store(Some(deadline));
+        self.wake_main_loop();
+    }
+
+    /// Cancels the timer set by [EventLoop::set_timer], if it hasn't fired yet.
+    pub fn cancel_timer(&self) {
+        self.timer.store(None);
+    }
+
+    /// Sets a timer (see [EventLoop::set_timer]) and waits until it fires.
+    ///
+    /// Events received in the meantime, including the one for the timer, are buffered
+    /// and sent to the event handler on the next call to [EventLoop::run] or associated methods.
+    pub async fn sleep_until(&self, deadline: Instant) {
+        self.set_timer(deadline);
+        timer::sleep_until(deadline).await
+    }
+
+    /// Waits for `duration`. Unlike [EventLoop::sleep_until] this doesn't set this proxy's timer,
+    /// it's woken directly by the main loop (see [timer::sleep]).
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        timer::sleep(duration)
+    }
+
+    /// Yields every `period` (see [timer::interval]).
+    pub fn interval(&self, period: Duration) -> Interval {
+        timer::interval(period)
+    }
+
+    /// Marks the window as needing a redraw. The main loop coalesces these, and at the next redraw
+    /// (limited by [crate::set_redraw_rate]) calls [Window::request_redraw], so the redraw is merged with
+    /// ones the OS requests and every proxy receives one [Event::RedrawRequested] for it.
+    ///
+    /// Takes an `Arc` so the main loop can request the redraw without keeping the window alive.
+    pub fn schedule_redraw(&self, window: &Arc<Window>) {
+        self.send_without_response(ProxyRequest::ScheduleRedraw { window_id: window.id(), window: Arc::downgrade(window) });
+        self.wake_main_loop();
+    }
+
+    /// Timing of the window's latest redraw, scheduled or not, or `None` if it was never redrawn
+    /// (see [EventLoop::schedule_redraw]).
+    ///
+    /// Updated when this proxy receives the redraw from the main loop, so in the handler for
+    /// [Event::RedrawRequested] it's the clock of that frame.
+    pub fn frame_clock(&self, window_id: WindowId) -> Option<FrameClock> {
+        self.frame_clocks.borrow().get(&window_id).copied()
+    }
+
+    /// Answers requests of type `Req` which other proxies send via [EventLoop::request].
+    /// Replaces the previous handler for `Req`, if any.
+    ///
+    /// Requests are only answered while this proxy is running or waiting for a response.
+    pub fn handle_requests<Req: Any + Send, Resp: Any + Send>(&self, mut handler: impl FnMut(Req) -> Resp + 'static) {
+        self.request_handlers.borrow_mut().insert(TypeId::of::<Req>(), Box::new(move |request| {
+            Box::new(handler(*request.downcast::<Req>().expect("request handler called with the wrong type")))
+        }));
+    }
+
+    /// Sends a request to the proxy with the given id and waits for its handler's answer (see [EventLoop::handle_requests]).
+    ///
+    /// Fails if the target proxy is dropped or has no handler for `Req`.
+    pub fn request<Req: Any + Send, Resp: Any + Send>(&self, target: ProxyId, request: Req) -> FutResponse<'_, Result<Resp, RequestError>, T> {
+        self._request(target, request, None)
+    }
+
+    /// Sends a request to the proxy with the given id and waits for its handler's answer (see [EventLoop::handle_requests]).
+    ///
+    /// Fails if the target proxy is dropped, has no handler for `Req`, or doesn't answer before `timeout`.
+    pub fn request_with_timeout<Req: Any + Send, Resp: Any + Send>(&self, target: ProxyId, request: Req, timeout: Duration) -> FutResponse<'_, Result<Resp, RequestError>, T> {
+        self._request(target, request, Some(timeout))
+    }
+
+    fn _request<Req: Any + Send, Resp: Any + Send>(&self, target: ProxyId, request: Req, timeout: Option<Duration>) -> FutResponse<'_, Result<Resp, RequestError>, T> {
+        let call_id = self.next_call_id.get();
+        self.next_call_id.set(call_id + 1);
+        self.send(ProxyRequest::Call {
+            call_id,
+            target,
+            request: Box::new(request),
+            timeout
+        }, |response| {
+            match response {
+                ProxyResponse::Call { call_id: _, result } => result.and_then(|response| {
+                    response.downcast::<Resp>().map(|response| *response).map_err(|_| RequestError::WrongResponseType)
+                }),
+                _ => panic!("incorrect response type, call responses were mixed up")
+            }
+        })
+    }
+
     /// Receives new *and buffered* events and responses from the main loop, blocking waiting for new responses,
     /// until the event handler explicitly exits.
     ///
-    /// The third argument to `event_handler` is whether the event is buffered (i.e. sent before this was called) or new.
-    pub fn run(&self, event_handler: impl FnMut(Event, &mut ControlFlow, EventIs)) {
+    /// The third argument to `event_handler` is the event's [EventMeta], including whether it's buffered
+    /// (i.e. sent before this was called) or new.
+    pub fn run(&self, event_handler: impl FnMut(Event<T>, &mut ControlFlow, EventMeta)) {
         block_on(self.run_async(event_handler))
     }
 
     /// Receives new *and buffered* events and responses from the main loop, blocking waiting for new responses,
     /// until the event handler explicitly exits.
     ///
-    /// The third argument to `event_handler` is whether the event is buffered (i.e. sent before this was called) or new.
-    pub async fn run_async(&self, mut event_handler: impl FnMut(Event, &mut ControlFlow, EventIs)) {
+    /// The third argument to `event_handler` is the event's [EventMeta], including whether it's buffered
+    /// (i.e. sent before this was called) or new.
+    pub async fn run_async(&self, mut event_handler: impl FnMut(Event<T>, &mut ControlFlow, EventMeta)) {
         assert!(!self.is_receiving_events.get(), "already running");
         self.is_receiving_events.set(true);
         // Handle locally pending events
-        for event in self.locally_pending_events.borrow_mut().drain(..) {
-            match self.handle_event(event, |event, control_flow| {
-                event_handler(event, control_flow, EventIs::New)
-            }) {
+        for (event, meta) in self.locally_pending_events.borrow_mut().drain(..) {
+            match self.handle_event(event, meta, &mut event_handler) {
                 std::ops::ControlFlow::Break(()) => {
                     // Exit early
                     self.is_receiving_events.set(false);
@@ -157,11 +502,12 @@ impl EventLoop {
     /// Receives new *and buffered* events and responses from the main loop, blocking waiting for new responses,
     /// until the event handler explicitly exits.
     ///
-    /// The third argument to `event_handler` is whether the event is buffered (i.e. sent before this was called) or new.
-    async fn _run_async(&self, mut event_handler: impl FnMut(Event, &mut ControlFlow, EventIs)) {
+    /// The third argument to `event_handler` is the event's [EventMeta], including whether it's buffered
+    /// (i.e. sent before this was called) or new.
+    async fn _run_async(&self, mut event_handler: impl FnMut(Event<T>, &mut ControlFlow, EventMeta)) {
         // Handle pending events
-        self.run_immediate(|event, control_flow| {
-            event_handler(event, control_flow, EventIs::Buffered);
+        self.run_immediate(|event, control_flow, meta| {
+            event_handler(event, control_flow, EventMeta { is: EventIs::Buffered, ..meta });
         });
 
         // Handle new events
@@ -171,9 +517,7 @@ impl EventLoop {
                 Err(_) => panic!("main event loop crashed")
             };
 
-            match self.handle_response(response, |event, control_flow| {
-                event_handler(event, control_flow, EventIs::New)
-            }) {
+            match self.handle_response(response, &mut event_handler) {
                 std::ops::ControlFlow::Break(()) => break,
                 std::ops::ControlFlow::Continue(()) => ()
             }
@@ -182,8 +526,11 @@ impl EventLoop {
 
     /// Receives all buffered events and responses from the main loop, not blocking for new events.
     ///
-    /// You can set [ControlFlow] to exit locally or exit the app, but [ControlFlow::Wait] and [ControlFlow::WaitUntil] won't do anything.
-    pub fn run_immediate(&self, mut event_handler: impl FnMut(Event, &mut ControlFlow)) {
+    /// You can set [ControlFlow] to exit locally or exit the app, but [ControlFlow::Wait], [ControlFlow::WaitUntil]
+    /// and [ControlFlow::Frame] won't do anything.
+    ///
+    /// The third argument to `event_handler` is the event's [EventMeta], like in [EventLoop::run].
+    pub fn run_immediate(&self, mut event_handler: impl FnMut(Event<T>, &mut ControlFlow, EventMeta)) {
         loop {
             let response = match self.recv.try_recv() {
                 Ok(response) => response,
@@ -201,17 +548,39 @@ impl EventLoop {
     fn handle_response(
         &self,
         response: ProxyResponse,
-        event_handler: impl FnMut(Event, &mut ControlFlow)
+        event_handler: impl FnMut(Event<T>, &mut ControlFlow, EventMeta)
     ) -> std::ops::ControlFlow<()> {
         // Events are separate from "regular" responses.
         // Events we just forward to the event handler,
         // other responses are associated with requests which need them in order to be resolved.
         // So the algorithm is:
+        // - If this is another proxy's request, answer it
+        // - If this is an answer to our request, resolve the request with the same id
         // - If this is an event, forward to the event handler
         // - Else there should be a pending request, resolve it
+        match response {
+            ProxyResponse::Called { call_id, request } => {
+                self.answer_call(call_id, request);
+                return std::ops::ControlFlow::Continue(())
+            }
+            ProxyResponse::FrameClock { window_id, clock } => {
+                self.frame_clocks.borrow_mut().insert(window_id, clock);
+                return std::ops::ControlFlow::Continue(())
+            }
+            ProxyResponse::Call { call_id, result } => {
+                let pending_call = self.pending_calls.borrow_mut().remove(&call_id).expect("unhandled call response with no associated request");
+                pending_call.resolve(ProxyResponse::Call { call_id, result });
+                return if self.is_receiving_events.get() || self.has_pending_requests() {
+                    std::ops::ControlFlow::Continue(())
+                } else {
+                    std::ops::ControlFlow::Break(())
+                }
+            }
+            _ => ()
+        }
         if self.is_receiving_events.get() {
-            if let ProxyResponse::Event(event) = response {
-                self.handle_event(event, event_handler)
+            if let ProxyResponse::Event(event, meta) = response {
+                self.handle_event(event, meta, event_handler)
             } else if let Some(pending_request) = self.pending_requests.borrow_mut().pop_front() {
                 pending_request.resolve(response);
                 std::ops::ControlFlow::Continue(())
@@ -220,12 +589,12 @@ impl EventLoop {
             }
         } else {
             let mut pending_requests = self.pending_requests.borrow_mut();
-            if let ProxyResponse::Event(event) = response {
-                self.locally_pending_events.borrow_mut().push(event);
+            if let ProxyResponse::Event(event, meta) = response {
+                self.locally_pending_events.borrow_mut().push((event, meta));
                 std::ops::ControlFlow::Continue(())
             } else if let Some(pending_request) = pending_requests.pop_front() {
                 pending_request.resolve(response);
-                if pending_requests.is_empty() {
+                if pending_requests.is_empty() && self.pending_calls.borrow().is_empty() {
                     // Only meant to receive responses, and we are done receiving them
                     std::ops::ControlFlow::Break(())
                 } else {
@@ -237,10 +606,87 @@ impl EventLoop {
         }
     }
 
-    fn handle_event(&self, event: Event, mut event_handler: impl FnMut(Event, &mut ControlFlow)) -> std::ops::ControlFlow<()> {
+    fn has_pending_requests(&self) -> bool {
+        !self.pending_requests.borrow().is_empty() || !self.pending_calls.borrow().is_empty()
+    }
+
+    fn answer_call(&self, call_id: u64, request: Box<dyn Any + Send>) {
+        let request_type = (*request).type_id();
+        // Take the handler out so it can register other handlers
+        let handler = self.request_handlers.borrow_mut().remove(&request_type);
+        let response = handler.map(|mut handler| {
+            let response = handler(request);
+            self.request_handlers.borrow_mut().entry(request_type).or_insert(handler);
+            response
+        });
+        self.send_without_response(ProxyRequest::Return { call_id, response });
+        self.wake_main_loop();
+    }
+
+    fn handle_event(&self, event: Event, meta: EventMeta, mut event_handler: impl FnMut(Event<T>, &mut ControlFlow, EventMeta)) -> std::ops::ControlFlow<()> {
+        // Track window states before the handler sees the event, and snapshots even if this proxy doesn't accept them
+        if let Some(window_states) = self.window_states.borrow_mut().as_mut() {
+            match &event {
+                Event::UserEvent(user_event) => if let Some(snapshot) = user_event.downcast_ref::<WindowSnapshot>() {
+                    window_states.apply_snapshot(snapshot);
+                },
+                event => window_states.handle_event(event)
+            }
+        }
+        if let Some(input) = self.input.borrow_mut().as_mut() {
+            input.handle_event(&event);
+        }
+        let is_main_events_cleared = matches!(event, Event::MainEventsCleared);
+        // User events which aren't `T` are not meant for this proxy
+        let event = match event.try_map_user_event(UserEvent::downcast::<T>) {
+            Ok(event) => event,
+            Err(_) => return std::ops::ControlFlow::Continue(())
+        };
+        // Time input from when the main loop received it, which may be long before buffered events are handled
+        let triggered_shortcuts = self.shortcuts.borrow_mut().handle_event(&event, meta.received);
+        let redraw_window_id = match &event {
+            Event::RedrawRequested(window_id) => Some(*window_id),
+            Event::WindowEvent { window_id, event: WindowEvent::Destroyed } => {
+                if let Some(frame_stats) = self.frame_stats.borrow_mut().as_mut() {
+                    frame_stats.remove(*window_id);
+                }
+                None
+            }
+            _ => None
+        };
         let mut control_flow = self.control_flow.load();
         debug_assert_ne!(control_flow, ControlFlow::ExitLocal);
-        event_handler(event, &mut control_flow);
+        event_handler(event, &mut control_flow, meta);
+
+        if is_main_events_cleared {
+            if let Some(input) = self.input.borrow_mut().as_mut() {
+                input.end_frame();
+            }
+        }
+
+        // Time redraws, and send reports and shortcuts after the event which made them due (with its meta)
+        let reports = match self.frame_stats.borrow_mut().as_mut() {
+            None => Vec::new(),
+            Some(frame_stats) => {
+                let end = Instant::now();
+                if let Some(window_id) = redraw_window_id {
+                    let scheduled = self.frame_clocks.borrow().get(&window_id).and_then(|clock| clock.scheduled);
+                    frame_stats.record(window_id, scheduled, meta.received, end);
+                }
+                frame_stats.take_due_reports(end)
+            }
+        };
+        let local_events = triggered_shortcuts.into_iter().map(UserEvent::new)
+            .chain(reports.into_iter().map(UserEvent::new));
+        for local_event in local_events {
+            if control_flow == ControlFlow::ExitLocal {
+                break
+            }
+            if let Ok(event) = Event::UserEvent(local_event).try_map_user_event(UserEvent::downcast::<T>) {
+                event_handler(event, &mut control_flow, meta);
+            }
+        }
+
         if control_flow == ControlFlow::ExitLocal {
             std::ops::ControlFlow::Break(())
         } else {
@@ -249,23 +695,148 @@ impl EventLoop {
         }
     }
 
-    fn send<T>(&self, message: ProxyRequest, convert_response: fn(ProxyResponse) -> T) -> FutResponse<'_, T> {
+    fn request_shortcut(&self, shortcut: Shortcut, scope: ShortcutScope) -> FutResponse<'_, Result<(), ShortcutConflict>, T> {
+        self.send(ProxyRequest::RegisterShortcut { shortcut, scope }, |response| {
+            match response {
+                ProxyResponse::RegisterShortcut { result } => result,
+                _ => panic!("incorrect response type, responses were received out-of-order")
+            }
+        })
+    }
+
+    /// Makes the main loop run, for state it only checks when it runs (timers, scheduled redraws and calls between proxies).
+    /// Otherwise, if every proxy is waiting, it would wait for an unrelated event
+    fn wake_main_loop(&self) {
+        // Fails if the main loop exited, then there's nothing to wake
+        let _ = self.main_proxy.send_event(MainEvent::Wake);
+    }
+
+    fn send<R>(&self, message: ProxyRequest, convert_response: fn(ProxyResponse) -> R) -> FutResponse<'_, R, T> {
         FutResponse::new(self, message, convert_response)
     }
 
+    fn send_without_response(&self, message: ProxyRequest) {
+        match self.send.try_send(message) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => unreachable!("proxy event loop channel (unbounded) is full?"),
+            Err(TrySendError::Disconnected(_)) => panic!("main event loop crashed")
+        }
+    }
+
     pub(crate) async fn actually_send(&self, message: ProxyRequest, waker: Waker, response_ptr: *mut Option<ProxyResponse>) {
+        // Calls to other proxies can be answered out-of-order
+        let call_id = match &message {
+            ProxyRequest::Call { call_id, .. } => Some(*call_id),
+            _ => None
+        };
         match self.send.try_send(message) {
             Ok(()) => (),
             Err(TrySendError::Full(_)) => unreachable!("proxy event loop channel (unbounded) is full?"),
             Err(TrySendError::Disconnected(_)) => panic!("main event loop crashed")
         };
+        if call_id.is_some() {
+            // Also starts the call's timeout now
+            self.wake_main_loop();
+        }
 
-        self.pending_requests.borrow_mut().push_back(PendingRequest::new(waker, response_ptr));
+        let pending_request = PendingRequest::new(waker, response_ptr);
+        match call_id {
+            None => self.pending_requests.borrow_mut().push_back(pending_request),
+            Some(call_id) => drop(self.pending_calls.borrow_mut().insert(call_id, pending_request))
+        }
 
         self.run_only_responses().await;
     }
 }
 
+/// Sends [UserEvent]s into the main event loop from any thread, waking it.
+/// Each event is then forwarded to every proxy [EventLoop] which accepts `T`.
+///
+/// Similar to [winit::event_loop::EventLoopProxy]. Get one from [EventLoop::create_proxy] or [crate::create_proxy].
+pub struct EventLoopProxy<T: 'static = UserEvent> {
+    proxy: winit::event_loop::EventLoopProxy<MainEvent>,
+    _user_event: PhantomData<fn(T)>
+}
+
+impl<T: UserEventTrait> EventLoopProxy<T> {
+    pub(crate) fn new() -> Self {
+        EventLoopProxy {
+            proxy: main_proxy(),
+            _user_event: PhantomData
+        }
+    }
+
+    /// Sends an event to every proxy [EventLoop] which accepts `T`, waking the main event loop.
+    ///
+    /// Returns an `Err` if the main event loop has stopped.
+    pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+        self.send_routed(Route::Broadcast, event)
+    }
+
+    /// Sends an event only to the proxy with the given id, if it exists and accepts `T`.
+    ///
+    /// Returns an `Err` if the main event loop has stopped.
+    pub fn send_event_to(&self, target: ProxyId, event: T) -> Result<(), EventLoopClosed<T>> {
+        self.send_routed(Route::Proxy(target), event)
+    }
+
+    /// Sends an event only to the proxies subscribed to `topic` (see [EventLoop::subscribe]) which accept `T`.
+    ///
+    /// Returns an `Err` if the main event loop has stopped.
+    pub fn publish(&self, topic: impl Into<String>, event: T) -> Result<(), EventLoopClosed<T>> {
+        self.send_routed(Route::Topic(topic.into()), event)
+    }
+
+    fn send_routed(&self, route: Route, event: T) -> Result<(), EventLoopClosed<T>> {
+        let event = UserEvent::new(event);
+        self.proxy.send_event(MainEvent::User { route, event }).map_err(|EventLoopClosed(event)| match event {
+            MainEvent::User { route: _, event } => EventLoopClosed(event.downcast().unwrap_or_else(|_| unreachable!("we just wrapped this event"))),
+            MainEvent::Wake | MainEvent::AddTimer(_) => unreachable!("we just sent a user event")
+        })
+    }
+}
+
+impl<T> Clone for EventLoopProxy<T> {
+    fn clone(&self) -> Self {
+        EventLoopProxy {
+            proxy: self.proxy.clone(),
+            _user_event: PhantomData
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for EventLoopProxy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad("EventLoopProxy { .. }")
+    }
+}
+
+/// Why a request to another proxy via [EventLoop::request] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestError {
+    /// The target proxy doesn't exist or was dropped before it answered
+    TargetGone,
+    /// The target proxy didn't answer before the timeout
+    TimedOut,
+    /// The target proxy has no handler for the request type
+    NoHandler,
+    /// The target proxy's handler returned a different type than the one requested
+    WrongResponseType
+}
+
+impl Display for RequestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::TargetGone => write!(f, "target proxy is gone"),
+            RequestError::TimedOut => write!(f, "target proxy didn't answer in time"),
+            RequestError::NoHandler => write!(f, "target proxy doesn't handle this request type"),
+            RequestError::WrongResponseType => write!(f, "target proxy answered with a different type")
+        }
+    }
+}
+
+impl Error for RequestError {}
+
 /// [winit::event_loop::ControlFlow] for a proxy event loop.
 ///
 /// Copied from [winit/event_loop](https://docs.rs/winit/0.26.1/src/winit/event_loop.rs.html) and modified.
@@ -281,10 +852,11 @@ impl EventLoop {
 /// while setting to [ExitApp] causes the entire application (including all other event loops) to exit.
 ///
 /// Defaults to [`Poll`].
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub enum ControlFlow {
     /// When the current loop iteration finishes, immediately begin a new iteration regardless of
     /// whether or not new events are available to process.
+    #[default]
     Poll,
     /// When the current loop iteration finishes, suspend the thread until another event arrives,
     /// if all other proxies are waiting.
@@ -293,8 +865,16 @@ pub enum ControlFlow {
     /// arrives, the given time is reached, or another proxy is receiving events.
     ///
     /// Can be useful for implementing timers but make sure the instant is actually reached because
-    /// of the "other proxies" policy.
+    /// of the "other proxies" policy: the [StartCause](winit::event::StartCause) will be [StartCause::ResumeTimeReached](winit::event::StartCause::ResumeTimeReached) once
+    /// it is. Also see [EventLoop::set_timer].
     WaitUntil(Instant),
+    /// When the current loop iteration finishes, suspend the thread until either another event
+    /// arrives, the next frame at the given rate (frames per second) is reached, or another proxy
+    /// is receiving events.
+    ///
+    /// Frames are aligned across proxies, so all proxies with the same rate wake up at the same time.
+    /// Unlike [Poll], this doesn't keep the main loop busy between frames.
+    Frame(u32),
     /// Stop this proxy and exit the corresponding [ProxyEventLoop::run] method this event handler
     /// was registered for.
     ExitLocal,
@@ -302,10 +882,11 @@ pub enum ControlFlow {
     ExitApp
 }
 
-impl Default for ControlFlow {
-    fn default() -> Self {
-        ControlFlow::Poll
-    }
+/// The first frame at `rate` frames per second after `now`, where frames are counted from `epoch`
+pub(crate) fn next_frame(epoch: Instant, rate: u32, now: Instant) -> Instant {
+    let period = (Duration::from_secs(1) / rate.max(1)).as_nanos();
+    let frames_elapsed = now.saturating_duration_since(epoch).as_nanos() / period;
+    epoch + Duration::from_nanos(((frames_elapsed + 1) * period) as u64)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -318,29 +899,51 @@ pub(crate) enum SharedControlFlow {
 
 impl PartialOrd for SharedControlFlow {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (SharedControlFlow::Wait, SharedControlFlow::Wait) => Some(Ordering::Equal),
-            (SharedControlFlow::WaitUntil(a), SharedControlFlow::WaitUntil(b)) => a.partial_cmp(&b),
-            (SharedControlFlow::Poll, SharedControlFlow::Poll) => Some(Ordering::Equal),
-            (SharedControlFlow::ExitApp, SharedControlFlow::ExitApp) => Some(Ordering::Equal),
-            (SharedControlFlow::Wait, SharedControlFlow::WaitUntil(_)) => Some(Ordering::Greater),
-            (SharedControlFlow::Wait, SharedControlFlow::Poll) => Some(Ordering::Greater),
-            (SharedControlFlow::Wait, SharedControlFlow::ExitApp) => Some(Ordering::Greater),
-            (SharedControlFlow::WaitUntil(_), SharedControlFlow::Poll) => Some(Ordering::Greater),
-            (SharedControlFlow::WaitUntil(_), SharedControlFlow::ExitApp) => Some(Ordering::Greater),
-            (SharedControlFlow::Poll, SharedControlFlow::ExitApp) => Some(Ordering::Greater),
-            (SharedControlFlow::WaitUntil(_), SharedControlFlow::Wait) => Some(Ordering::Less),
-            (SharedControlFlow::Poll, SharedControlFlow::Wait) => Some(Ordering::Less),
-            (SharedControlFlow::ExitApp, SharedControlFlow::Wait) => Some(Ordering::Less),
-            (SharedControlFlow::Poll, SharedControlFlow::WaitUntil(_)) => Some(Ordering::Less),
-            (SharedControlFlow::ExitApp, SharedControlFlow::WaitUntil(_)) => Some(Ordering::Less),
-            (SharedControlFlow::ExitApp, SharedControlFlow::Poll) => Some(Ordering::Less),
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for SharedControlFlow {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        match (self, other) {
+            (SharedControlFlow::Wait, SharedControlFlow::Wait) => Ordering::Equal,
+            (SharedControlFlow::WaitUntil(a), SharedControlFlow::WaitUntil(b)) => a.cmp(b),
+            (SharedControlFlow::Poll, SharedControlFlow::Poll) => Ordering::Equal,
+            (SharedControlFlow::ExitApp, SharedControlFlow::ExitApp) => Ordering::Equal,
+            (SharedControlFlow::Wait, SharedControlFlow::WaitUntil(_)) => Ordering::Greater,
+            (SharedControlFlow::Wait, SharedControlFlow::Poll) => Ordering::Greater,
+            (SharedControlFlow::Wait, SharedControlFlow::ExitApp) => Ordering::Greater,
+            (SharedControlFlow::WaitUntil(_), SharedControlFlow::Poll) => Ordering::Greater,
+            (SharedControlFlow::WaitUntil(_), SharedControlFlow::ExitApp) => Ordering::Greater,
+            (SharedControlFlow::Poll, SharedControlFlow::ExitApp) => Ordering::Greater,
+            (SharedControlFlow::WaitUntil(_), SharedControlFlow::Wait) => Ordering::Less,
+            (SharedControlFlow::Poll, SharedControlFlow::Wait) => Ordering::Less,
+            (SharedControlFlow::ExitApp, SharedControlFlow::Wait) => Ordering::Less,
+            (SharedControlFlow::Poll, SharedControlFlow::WaitUntil(_)) => Ordering::Less,
+            (SharedControlFlow::ExitApp, SharedControlFlow::WaitUntil(_)) => Ordering::Less,
+            (SharedControlFlow::ExitApp, SharedControlFlow::Poll) => Ordering::Less,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::next_frame;
+
+    #[test]
+    fn next_frame_is_aligned_to_epoch() {
+        let epoch = Instant::now();
+        let at = |millis| epoch + Duration::from_millis(millis);
+        assert_eq!(next_frame(epoch, 10, epoch), at(100));
+        assert_eq!(next_frame(epoch, 10, at(150)), at(200));
+        // Exactly on a frame waits for the next one, so a frame never fires twice
+        assert_eq!(next_frame(epoch, 10, at(200)), at(300));
+        // Late wakeups skip missed frames instead of catching up
+        assert_eq!(next_frame(epoch, 10, at(1234)), at(1300));
+        // Before the epoch counts as the epoch, and a 0 rate is treated as 1 fps
+        assert_eq!(next_frame(at(500), 10, epoch), at(600));
+        assert_eq!(next_frame(epoch, 0, at(10)), at(1000));
     }
 }
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use winit::dpi::PhysicalPosition;
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+
+/// Every file dropped on a window in one iteration, returned by [FileDropAggregator::handle_event]
+/// at [Event::MainEventsCleared].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDrop {
+    pub window_id: WindowId,
+    pub paths: Vec<PathBuf>,
+    /// The latest cursor position in the window, if known. Not every platform reports it during drags
+    pub position: Option<PhysicalPosition<f64>>
+}
+
+/// Files dragged over a window, returned like [FileDrop].
+/// A session is started, updated any number of times, then either cancelled or ended by a [FileDrop].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHover {
+    pub window_id: WindowId,
+    pub phase: FileHoverPhase,
+    /// Every file being dragged
+    pub paths: Vec<PathBuf>,
+    /// The latest cursor position in the window, if known
+    pub position: Option<PhysicalPosition<f64>>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileHoverPhase {
+    /// Files started hovering over the window
+    Started,
+    /// The cursor moved, or more files were hovered
+    Updated,
+    /// The files left the window without being dropped
+    Cancelled
+}
+
+/// A [FileDrop] or [FileHover] from [FileDropAggregator].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileDragEvent {
+    Drop(FileDrop),
+    Hover(FileHover)
+}
+
+/// Groups the [WindowEvent::HoveredFile] and [WindowEvent::DroppedFile] events winit sends for each file
+/// into one [FileHover] or [FileDrop] per window per iteration, and tracks hover sessions.
+#[derive(Debug, Clone, Default)]
+pub struct FileDropAggregator {
+    windows: HashMap<WindowId, WindowFiles>
+}
+
+#[derive(Debug, Clone, Default)]
+struct WindowFiles {
+    position: Option<PhysicalPosition<f64>>,
+    /// Files in the current hover session, `None` if there isn't one
+    hovered: Option<Vec<PathBuf>>,
+    /// Hovered this iteration and not yet reported
+    new_hovered: Vec<PathBuf>,
+    /// Dropped this iteration
+    dropped: Vec<PathBuf>
+}
+
+impl FileDropAggregator {
+    pub fn new() -> Self {
+        FileDropAggregator::default()
+    }
+
+    /// Whether files are being dragged over the window
+    pub fn is_hovering(&self, window_id: WindowId) -> bool {
+        self.windows.get(&window_id).is_some_and(|window| window.hovered.is_some())
+    }
+
+    /// Collects file events, returning the grouped events at [Event::MainEventsCleared].
+    /// Cursor movement and cancellation in a hover session are returned immediately
+    pub fn handle_event<T>(&mut self, event: &Event<T>) -> Vec<FileDragEvent> {
+        let (window_id, event) = match event {
+            Event::MainEventsCleared => return self.flush(),
+            Event::WindowEvent { window_id, event } => (*window_id, event),
+            _ => return Vec::new()
+        };
+        if let WindowEvent::Destroyed = event {
+            self.windows.remove(&window_id);
+            return Vec::new()
+        }
+        let window = self.windows.entry(window_id).or_default();
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                window.position = Some(*position);
+                if let Some(hovered) = &window.hovered {
+                    return vec![FileDragEvent::Hover(FileHover {
+                        window_id,
+                        phase: FileHoverPhase::Updated,
+                        paths: hovered.clone(),
+                        position: window.position
+                    })]
+                }
+            }
+            WindowEvent::CursorLeft { .. } => window.position = None,
+            WindowEvent::HoveredFile(path) => window.new_hovered.push(path.clone()),
+            WindowEvent::DroppedFile(path) => window.dropped.push(path.clone()),
+            WindowEvent::HoveredFileCancelled => {
+                window.new_hovered.clear();
+                if let Some(paths) = window.hovered.take() {
+                    return vec![FileDragEvent::Hover(FileHover {
+                        window_id,
+                        phase: FileHoverPhase::Cancelled,
+                        paths,
+                        position: window.position
+                    })]
+                }
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn flush(&mut self) -> Vec<FileDragEvent> {
+        let mut events = Vec::new();
+        for (window_id, window) in &mut self.windows {
+            if !window.new_hovered.is_empty() {
+                let phase = if window.hovered.is_none() { FileHoverPhase::Started } else { FileHoverPhase::Updated };
+                let hovered = window.hovered.get_or_insert_with(Vec::new);
+                hovered.append(&mut window.new_hovered);
+                events.push(FileDragEvent::Hover(FileHover {
+                    window_id: *window_id,
+                    phase,
+                    paths: hovered.clone(),
+                    position: window.position
+                }));
+            }
+            if !window.dropped.is_empty() {
+                // Dropping ends the hover session
+                window.hovered = None;
+                events.push(FileDragEvent::Drop(FileDrop {
+                    window_id: *window_id,
+                    paths: std::mem::take(&mut window.dropped),
+                    position: window.position
+                }));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::event::{Event, WindowEvent};
+    use crate::test_support::window_id;
+    use super::{FileDragEvent, FileDrop, FileDropAggregator, FileHover, FileHoverPhase};
+
+    fn window_event(event: WindowEvent) -> Event {
+        Event::WindowEvent { window_id: window_id(), event }
+    }
+
+    fn main_events_cleared() -> Event {
+        Event::MainEventsCleared
+    }
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    fn hover(phase: FileHoverPhase, names: &[&str]) -> FileDragEvent {
+        FileDragEvent::Hover(FileHover { window_id: window_id(), phase, paths: paths(names), position: None })
+    }
+
+    fn dropped(names: &[&str]) -> FileDragEvent {
+        FileDragEvent::Drop(FileDrop { window_id: window_id(), paths: paths(names), position: None })
+    }
+
+    #[test]
+    fn groups_multi_file_drops() {
+        let mut aggregator = FileDropAggregator::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert_eq!(aggregator.handle_event(&window_event(WindowEvent::DroppedFile(PathBuf::from(name)))), []);
+        }
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), [dropped(&["a.txt", "b.txt", "c.txt"])]);
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), []);
+    }
+
+    #[test]
+    fn cancels_hover() {
+        let mut aggregator = FileDropAggregator::new();
+        aggregator.handle_event(&window_event(WindowEvent::HoveredFile(PathBuf::from("a.txt"))));
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), [hover(FileHoverPhase::Started, &["a.txt"])]);
+        assert!(aggregator.is_hovering(window_id()));
+
+        aggregator.handle_event(&window_event(WindowEvent::HoveredFile(PathBuf::from("b.txt"))));
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), [hover(FileHoverPhase::Updated, &["a.txt", "b.txt"])]);
+
+        assert_eq!(
+            aggregator.handle_event(&window_event(WindowEvent::HoveredFileCancelled)),
+            [hover(FileHoverPhase::Cancelled, &["a.txt", "b.txt"])]
+        );
+        assert!(!aggregator.is_hovering(window_id()));
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), []);
+
+        // Cancelled before it was reported, so there's nothing to report
+        aggregator.handle_event(&window_event(WindowEvent::HoveredFile(PathBuf::from("c.txt"))));
+        assert_eq!(aggregator.handle_event(&window_event(WindowEvent::HoveredFileCancelled)), []);
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), []);
+    }
+
+    #[test]
+    fn hover_then_drop_in_one_iteration() {
+        let mut aggregator = FileDropAggregator::new();
+        for name in ["a.txt", "b.txt"] {
+            aggregator.handle_event(&window_event(WindowEvent::HoveredFile(PathBuf::from(name))));
+        }
+        for name in ["a.txt", "b.txt"] {
+            aggregator.handle_event(&window_event(WindowEvent::DroppedFile(PathBuf::from(name))));
+        }
+        assert_eq!(aggregator.handle_event(&main_events_cleared()), [
+            hover(FileHoverPhase::Started, &["a.txt", "b.txt"]),
+            dropped(&["a.txt", "b.txt"])
+        ]);
+        assert!(!aggregator.is_hovering(window_id()));
+    }
+}
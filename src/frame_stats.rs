@@ -0,0 +1,238 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use winit::window::WindowId;
+
+/// Settings for frame statistics, enabled by [EventLoop::enable_frame_stats](crate::event_loop::EventLoop::enable_frame_stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStatsConfig {
+    /// Expected frames per second. A scheduled redraw which comes more than one and a half periods after it was
+    /// scheduled (or after the previous frame, if it was scheduled before) counts as dropping a frame for every
+    /// additional period. Time when no redraw was pending doesn't count
+    pub target_rate: u32,
+    /// How many of the latest frames the averages and percentiles are over
+    pub sample_count: usize,
+    /// How often this proxy receives a [FrameStatsReport] for each window, or `None` to only query
+    pub report_interval: Option<Duration>
+}
+
+/// Statistics for a window's latest frames, measured by the proxy handling its
+/// [Event::RedrawRequested](crate::event::Event::RedrawRequested).
+///
+/// "Frame time" is from when the main loop received the redraw until the event handler finished handling it,
+/// "frame interval" is the time between consecutive redraws reaching the main loop. Both use [EventMeta::received](crate::event_loop::EventMeta::received),
+/// so they're accurate for buffered redraws too. "Latency" is from when the redraw was scheduled
+/// (see [FrameClock::scheduled](crate::redraw::FrameClock::scheduled)) until the event handler finished handling it,
+/// so it's only measured for scheduled redraws.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// Frames since stats were enabled
+    pub frames: u64,
+    /// Frames dropped since stats were enabled, see [FrameStatsConfig::target_rate]
+    pub dropped_frames: u64,
+    /// Frames per second, from the average frame interval
+    pub fps: f64,
+    pub average_frame_interval: Duration,
+    pub average_frame_time: Duration,
+    /// Median frame time
+    pub p50_frame_time: Duration,
+    pub p95_frame_time: Duration,
+    pub p99_frame_time: Duration,
+    pub max_frame_time: Duration,
+    /// Average latency, or zero if none of the latest frames were scheduled
+    pub average_latency: Duration,
+    pub max_latency: Duration
+}
+
+/// User event with a window's [FrameStats], sent to the proxy collecting them every
+/// [FrameStatsConfig::report_interval]. Only received if the proxy accepts this type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStatsReport {
+    pub window_id: WindowId,
+    pub stats: FrameStats
+}
+
+/// Proxy state for frame statistics
+#[derive(Debug)]
+pub(crate) struct FrameStatsCollector {
+    config: FrameStatsConfig,
+    windows: HashMap<WindowId, WindowSamples>,
+    last_report: Instant
+}
+
+#[derive(Debug, Default)]
+struct WindowSamples {
+    frames: u64,
+    dropped_frames: u64,
+    last_received: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+    frame_intervals: VecDeque<Duration>,
+    latencies: VecDeque<Duration>
+}
+
+impl Default for FrameStatsConfig {
+    /// 60 FPS target, stats over the last 120 frames, reported every second
+    fn default() -> Self {
+        FrameStatsConfig {
+            target_rate: 60,
+            sample_count: 120,
+            report_interval: Some(Duration::from_secs(1))
+        }
+    }
+}
+
+impl FrameStatsCollector {
+    pub(crate) fn new(config: FrameStatsConfig) -> Self {
+        FrameStatsCollector {
+            config,
+            windows: HashMap::new(),
+            last_report: Instant::now()
+        }
+    }
+
+    /// Records a redraw of the window which was scheduled at `scheduled` (`None` if it wasn't),
+    /// the main loop received at `received`, and whose handler finished at `end`
+    pub(crate) fn record(&mut self, window_id: WindowId, scheduled: Option<Instant>, received: Instant, end: Instant) {
+        let sample_count = self.config.sample_count.max(1);
+        let period = Duration::from_secs(1) / self.config.target_rate.max(1);
+        let samples = self.windows.entry(window_id).or_default();
+
+        samples.frames += 1;
+        if let Some(scheduled) = scheduled {
+            // A redraw scheduled before the previous frame was only pending since then
+            let pending_since = samples.last_received.map_or(scheduled, |last_received| last_received.max(scheduled));
+            let pending = received.saturating_duration_since(pending_since);
+            // Rounded number of periods the redraw was pending, minus the one we expect
+            let periods = ((pending + period / 2).as_nanos() / period.as_nanos()) as u64;
+            samples.dropped_frames += periods.saturating_sub(1);
+            push_sample(&mut samples.latencies, end.saturating_duration_since(scheduled), sample_count);
+        }
+        if let Some(last_received) = samples.last_received {
+            push_sample(&mut samples.frame_intervals, received.saturating_duration_since(last_received), sample_count);
+        }
+        samples.last_received = Some(received);
+        push_sample(&mut samples.frame_times, end.saturating_duration_since(received), sample_count);
+    }
+
+    pub(crate) fn stats(&self, window_id: WindowId) -> Option<FrameStats> {
+        self.windows.get(&window_id).map(WindowSamples::stats)
+    }
+
+    /// Reports for every window if the report interval passed
+    pub(crate) fn take_due_reports(&mut self, now: Instant) -> Vec<FrameStatsReport> {
+        match self.config.report_interval {
+            Some(report_interval) if now.saturating_duration_since(self.last_report) >= report_interval => {
+                self.last_report = now;
+                self.windows.iter().map(|(window_id, samples)| FrameStatsReport {
+                    window_id: *window_id,
+                    stats: samples.stats()
+                }).collect()
+            }
+            _ => Vec::new()
+        }
+    }
+
+    /// Forgets a destroyed window
+    pub(crate) fn remove(&mut self, window_id: WindowId) {
+        self.windows.remove(&window_id);
+    }
+}
+
+impl WindowSamples {
+    fn stats(&self) -> FrameStats {
+        let average_frame_interval = average(&self.frame_intervals);
+        let mut sorted_frame_times = self.frame_times.iter().copied().collect::<Vec<_>>();
+        sorted_frame_times.sort_unstable();
+        FrameStats {
+            frames: self.frames,
+            dropped_frames: self.dropped_frames,
+            fps: if average_frame_interval.is_zero() { 0.0 } else { 1.0 / average_frame_interval.as_secs_f64() },
+            average_frame_interval,
+            average_frame_time: average(&self.frame_times),
+            p50_frame_time: percentile(&sorted_frame_times, 50),
+            p95_frame_time: percentile(&sorted_frame_times, 95),
+            p99_frame_time: percentile(&sorted_frame_times, 99),
+            max_frame_time: sorted_frame_times.last().copied().unwrap_or_default(),
+            average_latency: average(&self.latencies),
+            max_latency: self.latencies.iter().max().copied().unwrap_or_default()
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<Duration>, sample: Duration, sample_count: usize) {
+    if samples.len() == sample_count {
+        samples.pop_front();
+    }
+    samples.push_back(sample);
+}
+
+fn average(samples: &VecDeque<Duration>) -> Duration {
+    if samples.is_empty() {
+        Duration::ZERO
+    } else {
+        samples.iter().sum::<Duration>() / samples.len() as u32
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted_samples: &[Duration], percent: usize) -> Duration {
+    if sorted_samples.is_empty() {
+        Duration::ZERO
+    } else {
+        let rank = (percent * sorted_samples.len()).div_ceil(100).max(1);
+        sorted_samples[rank - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::time::{Duration, Instant};
+    use crate::test_support::window_id;
+    use super::{average, percentile, FrameStatsCollector, FrameStatsConfig};
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn averages_and_percentiles() {
+        let samples = (1..=10).map(millis).collect::<VecDeque<_>>();
+        assert_eq!(average(&samples), Duration::from_micros(5500));
+        assert_eq!(average(&VecDeque::new()), Duration::ZERO);
+
+        let sorted_samples = samples.into_iter().collect::<Vec<_>>();
+        assert_eq!(percentile(&sorted_samples, 50), millis(5));
+        assert_eq!(percentile(&sorted_samples, 95), millis(10));
+        assert_eq!(percentile(&sorted_samples, 0), millis(1));
+        assert_eq!(percentile(&sorted_samples[..1], 99), millis(1));
+        assert_eq!(percentile(&[], 50), Duration::ZERO);
+    }
+
+    #[test]
+    fn counts_drops_only_while_pending() {
+        let epoch = Instant::now();
+        let at = |at| epoch + millis(at);
+        let mut collector = FrameStatsCollector::new(FrameStatsConfig { target_rate: 10, sample_count: 2, report_interval: None });
+
+        // Idle between redraws the OS requested
+        collector.record(window_id(), None, at(0), at(10));
+        collector.record(window_id(), None, at(1000), at(1010));
+        assert_eq!(collector.stats(window_id()).unwrap().dropped_frames, 0);
+
+        // Pending for 3 periods
+        collector.record(window_id(), Some(at(1000)), at(1300), at(1310));
+        // Scheduled before the previous frame, so only pending for 1 period
+        collector.record(window_id(), Some(at(1200)), at(1400), at(1410));
+
+        let stats = collector.stats(window_id()).unwrap();
+        assert_eq!((stats.frames, stats.dropped_frames), (4, 2));
+        // Over the last 2 samples
+        assert_eq!(stats.average_frame_interval, millis(200));
+        assert_eq!(stats.fps, 5.0);
+        assert_eq!((stats.average_frame_time, stats.max_frame_time), (millis(10), millis(10)));
+        assert_eq!((stats.average_latency, stats.max_latency), (millis(260), millis(310)));
+
+        collector.remove(window_id());
+        assert_eq!(collector.stats(window_id()), None);
+    }
+}
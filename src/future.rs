@@ -1,35 +1,38 @@
 use std::task::{Context, Poll, Waker};
 use std::future::Future;
-use std::marker::PhantomPinned;
+use std::marker::{PhantomData, PhantomPinned};
 use std::mem::ManuallyDrop;
 use std::pin::Pin;
 use std::sync::Arc;
 use crossbeam_utils::atomic::AtomicCell;
+use crate::event::{UserEvent, UserEventTrait};
 use crate::event_loop::EventLoop;
 use crate::messages::{ProxyRegisterBody, ProxyRequest, ProxyResponse};
 
 /// Future [EventLoop]
-pub struct FutEventLoop {
-    pub(crate) body: Arc<AtomicCell<ProxyRegisterBody>>
+pub struct FutEventLoop<T: 'static = UserEvent> {
+    pub(crate) body: Arc<AtomicCell<ProxyRegisterBody>>,
+    pub(crate) snapshot: bool,
+    pub(crate) _user_event: PhantomData<fn() -> T>
 }
 
 /// Future `T` which we get by an RPC from a proxy [EventLoop] to the main thread.
 #[must_use = "the response won't actually send until you await or poll"]
 #[repr(C)]
-pub struct FutResponse<'a, T>(
+pub struct FutResponse<'a, R, T: 'static = UserEvent>(
     // actually_send passes a reference to this, we want to keep it alive until that reference is set and this is polled again.
-    ManuallyDrop<_FutResponse<'a, T>>
+    ManuallyDrop<_FutResponse<'a, R, T>>
 );
 
 
 #[must_use = "the response won't actually send until you await or poll"]
 #[repr(C)]
-struct _FutResponse<'a, T> {
+struct _FutResponse<'a, R, T: 'static> {
     response: Option<ProxyResponse>,
     held_future: Option<Box<dyn Future<Output=()> + 'a>>,
     message: Option<ProxyRequest>,
-    proxy: &'a EventLoop,
-    convert: fn(ProxyResponse) -> T,
+    proxy: &'a EventLoop<T>,
+    convert: fn(ProxyResponse) -> R,
     // This is pinned because there is a pointer to response in PendingRequest
     _p: PhantomPinned
 }
@@ -39,8 +42,18 @@ pub(crate) struct PendingRequest {
     response_ptr: *mut Option<ProxyResponse>
 }
 
-impl Future for FutEventLoop {
-    type Output = EventLoop;
+impl<T> FutEventLoop<T> {
+    /// Once registered, the proxy receives a [WindowSnapshot](crate::window_state::WindowSnapshot) user event
+    /// with the state of every existing window. It reflects every event the proxy received before it.
+    /// The proxy must accept [WindowSnapshot](crate::window_state::WindowSnapshot) user events to receive it.
+    pub fn with_snapshot(mut self) -> Self {
+        self.snapshot = true;
+        self
+    }
+}
+
+impl<T: UserEventTrait> Future for FutEventLoop<T> {
+    type Output = EventLoop<T>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.body.take() {
@@ -49,16 +62,22 @@ impl Future for FutEventLoop {
                 Poll::Pending
             }
             ProxyRegisterBody::Polled { waker: _ } => panic!("polled redundantly"),
-            ProxyRegisterBody::Ready { info } => Poll::Ready(EventLoop::from(info))
+            ProxyRegisterBody::Ready { info } => {
+                let event_loop = EventLoop::from(info);
+                if self.snapshot {
+                    event_loop.request_snapshot_event();
+                }
+                Poll::Ready(event_loop)
+            }
         }
     }
 }
 
-impl<'a, T> FutResponse<'a, T> {
+impl<'a, R, T: UserEventTrait> FutResponse<'a, R, T> {
     pub(crate) fn new(
-        proxy: &'a EventLoop,
+        proxy: &'a EventLoop<T>,
         message: ProxyRequest,
-        convert: fn(ProxyResponse) -> T
+        convert: fn(ProxyResponse) -> R
     ) -> Self {
         FutResponse(ManuallyDrop::new(_FutResponse {
             response: None,
@@ -70,7 +89,7 @@ impl<'a, T> FutResponse<'a, T> {
         }))
     }
 
-    fn finalize(&mut self, response: ProxyResponse) -> Poll<T> {
+    fn finalize(&mut self, response: ProxyResponse) -> Poll<R> {
         let convert = self.0.convert;
         // SAFETY: Once we return we no longer need this
         unsafe { ManuallyDrop::drop(&mut self.0) };
@@ -78,8 +97,8 @@ impl<'a, T> FutResponse<'a, T> {
     }
 }
 
-impl<'a, T> Future for FutResponse<'a, T> {
-    type Output = T;
+impl<'a, R, T: UserEventTrait> Future for FutResponse<'a, R, T> {
+    type Output = R;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // SAFETY
@@ -0,0 +1,246 @@
+use std::time::{Duration, Instant};
+use futures::executor::block_on;
+use winit::event::StartCause;
+use crate::event::{Event, UserEvent, UserEventTrait};
+use crate::event_loop::{ControlFlow, EventLoop};
+
+/// Settings for a fixed-timestep game loop, run by [EventLoop::run_game].
+///
+/// The game is updated at a fixed rate, independent of how often it's rendered.
+/// When rendering falls behind, the game catches up with multiple updates per frame, up to a limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameLoop {
+    /// Fixed updates per second
+    pub tick_rate: u32,
+    /// Frames rendered per second, or `None` to render once per update
+    pub render_rate: Option<u32>,
+    /// Maximum updates per frame. If the game is still behind after these, the extra time is dropped
+    /// instead of making the next frame even slower ("spiral of death")
+    pub max_catch_up_steps: u32,
+    /// Maximum time between frames which is simulated. Longer frames (e.g. after the app was
+    /// suspended or stopped in a debugger) are treated as this long
+    pub max_frame_time: Duration
+}
+
+/// Callbacks for [EventLoop::run_game].
+///
+/// The callbacks get the game's own [ControlFlow], which persists between calls and starts as [ControlFlow::Wait].
+/// While it's `Wait`, the proxy waits for the next update or frame. Anything else (e.g. [ControlFlow::Poll] or exiting)
+/// is used as is, until the game sets it back to `Wait`.
+pub trait Game<T = UserEvent> {
+    /// Handles an event, before the updates and render of the frame it's in
+    fn event(&mut self, _event: Event<T>, _control_flow: &mut ControlFlow) {}
+    /// Advances the game by exactly `dt`, which is `1 / tick_rate`
+    fn update(&mut self, dt: Duration, control_flow: &mut ControlFlow);
+    /// Renders the game. `alpha` (between 0 and 1) is how far the current time is between the last
+    /// update and the next one, so you can interpolate
+    fn render(&mut self, alpha: f64, control_flow: &mut ControlFlow);
+}
+
+/// Time accounting for [EventLoop::run_game]
+struct GameClock {
+    game_loop: GameLoop,
+    dt: Duration,
+    last_frame: Option<Instant>,
+    accumulator: Duration,
+    /// What to wait for after the latest frame
+    wait: ControlFlow
+}
+
+impl GameLoop {
+    /// Updates `tick_rate` times per second and renders after each update,
+    /// catching up at most 5 updates per frame and 250ms per frame.
+    pub fn new(tick_rate: u32) -> Self {
+        GameLoop {
+            tick_rate,
+            render_rate: None,
+            max_catch_up_steps: 5,
+            max_frame_time: Duration::from_millis(250)
+        }
+    }
+}
+
+impl Default for GameLoop {
+    fn default() -> Self {
+        GameLoop::new(60)
+    }
+}
+
+impl GameClock {
+    fn new(game_loop: GameLoop) -> Self {
+        GameClock {
+            game_loop,
+            dt: Duration::from_secs(1) / game_loop.tick_rate.max(1),
+            last_frame: None,
+            accumulator: Duration::ZERO,
+            wait: ControlFlow::Poll
+        }
+    }
+
+    /// Accounts for the time since the last frame, for an [Event::NewEvents] the main loop received at `received`
+    fn new_events(&mut self, cause: StartCause, received: Instant) {
+        let now = match cause {
+            // This is the time we actually wanted to wake up, so ticks don't drift
+            StartCause::ResumeTimeReached { requested_resume, .. } => requested_resume,
+            StartCause::WaitCancelled { .. } | StartCause::Poll | StartCause::Init => received
+        };
+        if let Some(last_frame) = self.last_frame {
+            self.accumulator += now.saturating_duration_since(last_frame).min(self.game_loop.max_frame_time);
+        }
+        self.last_frame = Some(now);
+    }
+
+    /// Restarts timing, so time spent suspended isn't simulated
+    fn reset(&mut self) {
+        self.last_frame = None;
+        self.accumulator = Duration::ZERO;
+    }
+
+    /// Runs the updates which are due, then renders. `control_flow` is the game's
+    fn frame<T>(&mut self, game: &mut impl Game<T>, control_flow: &mut ControlFlow) {
+        let mut steps = 0;
+        while self.accumulator >= self.dt && steps < self.game_loop.max_catch_up_steps {
+            game.update(self.dt, control_flow);
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+        if self.accumulator >= self.dt {
+            // Too far behind, drop the whole ticks we couldn't catch up on
+            self.accumulator = Duration::from_nanos((self.accumulator.as_nanos() % self.dt.as_nanos()) as u64);
+        }
+
+        game.render(self.accumulator.as_secs_f64() / self.dt.as_secs_f64(), control_flow);
+
+        self.wait = match (self.game_loop.render_rate, self.last_frame) {
+            (Some(render_rate), _) => ControlFlow::Frame(render_rate),
+            (None, Some(last_frame)) => ControlFlow::WaitUntil(last_frame + (self.dt - self.accumulator)),
+            // No NewEvents yet to time from
+            (None, None) => ControlFlow::Poll
+        };
+    }
+
+    /// The proxy's control flow given the game's
+    fn control_flow(&self, game_control_flow: ControlFlow) -> ControlFlow {
+        match game_control_flow {
+            ControlFlow::Wait => self.wait,
+            game_control_flow => game_control_flow
+        }
+    }
+}
+
+impl<T: UserEventTrait> EventLoop<T> {
+    /// Runs a fixed-timestep game loop: [Game::update] is called `tick_rate` times per second
+    /// and [Game::render] after the updates in each frame, until the game sets [ControlFlow] to exit.
+    ///
+    /// Timing comes from [Event::NewEvents], and updates and rendering happen on [Event::MainEventsCleared].
+    pub fn run_game(&self, game_loop: GameLoop, game: &mut impl Game<T>) {
+        block_on(self.run_game_async(game_loop, game))
+    }
+
+    /// Runs a fixed-timestep game loop, see [EventLoop::run_game].
+    pub async fn run_game_async(&self, game_loop: GameLoop, game: &mut impl Game<T>) {
+        let mut clock = GameClock::new(game_loop);
+        let mut game_control_flow = ControlFlow::Wait;
+        self.run_async(|event, control_flow, meta| {
+            match &event {
+                Event::NewEvents(cause) => clock.new_events(*cause, meta.received),
+                Event::Suspended => clock.reset(),
+                _ => {}
+            }
+            let is_main_events_cleared = matches!(event, Event::MainEventsCleared);
+            game.event(event, &mut game_control_flow);
+            if is_main_events_cleared {
+                clock.frame(game, &mut game_control_flow);
+            }
+            *control_flow = clock.control_flow(game_control_flow);
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use winit::event::StartCause;
+    use crate::event_loop::ControlFlow;
+    use super::{Game, GameClock, GameLoop};
+
+    #[derive(Default)]
+    struct Recorder {
+        updates: u32,
+        alphas: Vec<f64>
+    }
+
+    impl Game for Recorder {
+        fn update(&mut self, dt: Duration, _control_flow: &mut ControlFlow) {
+            assert_eq!(dt, Duration::from_millis(100));
+            self.updates += 1;
+        }
+
+        fn render(&mut self, alpha: f64, _control_flow: &mut ControlFlow) {
+            self.alphas.push(alpha);
+        }
+    }
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn cancelled(start: Instant) -> StartCause {
+        StartCause::WaitCancelled { start, requested_resume: None }
+    }
+
+    #[test]
+    fn accumulates_time_between_events() {
+        let epoch = Instant::now();
+        let mut clock = GameClock::new(GameLoop::new(10));
+        let mut game = Recorder::default();
+        let mut control_flow = ControlFlow::Wait;
+
+        clock.new_events(StartCause::Init, epoch);
+        clock.frame(&mut game, &mut control_flow);
+        assert_eq!((game.updates, game.alphas.as_slice()), (0, [0.0].as_slice()));
+        assert_eq!(clock.control_flow(control_flow), ControlFlow::WaitUntil(epoch + millis(100)));
+
+        // Timed from when the main loop received the event, not when the wait started
+        clock.new_events(cancelled(epoch), epoch + millis(250));
+        clock.frame(&mut game, &mut control_flow);
+        assert_eq!((game.updates, game.alphas[1]), (2, 0.5));
+        assert_eq!(clock.control_flow(control_flow), ControlFlow::WaitUntil(epoch + millis(300)));
+
+        // Timed from the requested resume, even if the event was received later
+        clock.new_events(StartCause::ResumeTimeReached { start: epoch, requested_resume: epoch + millis(300) }, epoch + millis(320));
+        clock.frame(&mut game, &mut control_flow);
+        assert_eq!((game.updates, game.alphas[2]), (3, 0.0));
+
+        // The game's own control flow wins
+        control_flow = ControlFlow::Poll;
+        assert_eq!(clock.control_flow(control_flow), ControlFlow::Poll);
+    }
+
+    #[test]
+    fn caps_catch_up() {
+        let epoch = Instant::now();
+        let mut clock = GameClock::new(GameLoop { max_catch_up_steps: 3, max_frame_time: Duration::from_secs(1), ..GameLoop::new(10) });
+        let mut game = Recorder::default();
+        let mut control_flow = ControlFlow::Wait;
+
+        clock.new_events(StartCause::Init, epoch);
+        // 650ms behind after 3 updates, so the 6 whole ticks are dropped
+        clock.new_events(cancelled(epoch), epoch + millis(950));
+        clock.frame(&mut game, &mut control_flow);
+        assert_eq!(game.updates, 3);
+        assert!((game.alphas[0] - 0.5).abs() < 1e-9);
+
+        // Longer than max_frame_time is simulated as max_frame_time
+        clock.new_events(cancelled(epoch), epoch + millis(10950));
+        clock.frame(&mut game, &mut control_flow);
+        assert_eq!(game.updates, 6);
+        assert!((game.alphas[1] - 0.5).abs() < 1e-9);
+
+        // Suspending doesn't simulate the time
+        clock.reset();
+        clock.new_events(StartCause::Init, epoch + millis(20000));
+        clock.frame(&mut game, &mut control_flow);
+        assert_eq!((game.updates, game.alphas[2]), (6, 0.0));
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalPosition;
+use winit::event::{Touch, TouchPhase};
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+
+/// Thresholds for [GestureRecognizer].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// Longest a touch can be held to count as a tap
+    pub tap_max_duration: Duration,
+    /// Farthest (in physical pixels) a touch can move to count as a tap or long press
+    pub tap_max_distance: f64,
+    /// Longest time between taps to count as a double tap
+    pub double_tap_max_interval: Duration,
+    /// How long a touch must be held still to count as a long press
+    pub long_press_duration: Duration,
+    /// How far (in physical pixels) a single touch must move to start panning
+    pub pan_min_distance: f64,
+    /// How much the distance between two touches must change (as a fraction) to start pinching
+    pub pinch_min_scale: f64,
+    /// How much the angle between two touches must change (in radians) to start rotating
+    pub rotate_min_angle: f64
+}
+
+/// Where a continuous gesture is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GesturePhase {
+    Started,
+    Changed,
+    Ended,
+    /// The touches were cancelled by the OS
+    Cancelled
+}
+
+/// A gesture recognized from a window's touches, see [GestureRecognizer].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    Tap { position: PhysicalPosition<f64> },
+    /// Sent after the second [Gesture::Tap]
+    DoubleTap { position: PhysicalPosition<f64> },
+    /// A touch was held still. It won't also be a tap
+    LongPress { position: PhysicalPosition<f64> },
+    /// A single touch moved. `translation` is from where the touch started, `delta` from the previous pan event
+    Pan {
+        phase: GesturePhase,
+        position: PhysicalPosition<f64>,
+        delta: (f64, f64),
+        translation: (f64, f64)
+    },
+    /// Two touches moved apart or together. `scale` is the distance between them relative to when they
+    /// started, `delta_scale` relative to the previous pinch event
+    Pinch {
+        phase: GesturePhase,
+        center: PhysicalPosition<f64>,
+        scale: f64,
+        delta_scale: f64
+    },
+    /// Two touches turned. `angle` is in radians (clockwise in window coordinates) since they started,
+    /// `delta_angle` since the previous rotate event
+    Rotate {
+        phase: GesturePhase,
+        center: PhysicalPosition<f64>,
+        angle: f64,
+        delta_angle: f64
+    },
+    /// The touchpad was pressed harder, to a new stage (see [WindowEvent::TouchpadPressure]). Stage 2 is a force click
+    ForcePress { stage: i64, pressure: f32 }
+}
+
+/// A [Gesture] in a window, returned by [GestureRecognizer::handle_event] and [GestureRecognizer::poll].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureEvent {
+    pub window_id: WindowId,
+    pub gesture: Gesture
+}
+
+/// Recognizes taps, double taps, long presses, pans, pinches, rotations and force presses per window,
+/// from [WindowEvent::Touch] and [WindowEvent::TouchpadPressure].
+///
+/// Time is passed in explicitly, so you can feed it synthetic touch sequences. Long presses happen
+/// without an event, so call [GestureRecognizer::poll] at [GestureRecognizer::next_deadline],
+/// e.g. by setting [ControlFlow::WaitUntil](crate::event_loop::ControlFlow::WaitUntil) or a timer
+/// via [EventLoop::set_timer](crate::event_loop::EventLoop::set_timer).
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    windows: HashMap<WindowId, WindowGestures>
+}
+
+#[derive(Debug, Clone, Default)]
+struct WindowGestures {
+    touches: Vec<TrackedTouch>,
+    /// Most touches down at once since all were lifted. Only single touches are taps and long presses
+    max_touches: usize,
+    pan: Option<Pan>,
+    transform: Option<Transform>,
+    last_tap: Option<(Instant, PhysicalPosition<f64>)>,
+    pressure_stage: i64
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedTouch {
+    id: u64,
+    start: PhysicalPosition<f64>,
+    start_time: Instant,
+    position: PhysicalPosition<f64>,
+    /// Moved too far or already long pressed, so it won't be a tap or long press
+    is_used: bool
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pan {
+    start: PhysicalPosition<f64>,
+    last: PhysicalPosition<f64>
+}
+
+/// Two-touch tracking for pinches and rotations
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    initial_distance: f64,
+    initial_angle: f64,
+    last_distance: f64,
+    last_angle: f64,
+    is_pinching: bool,
+    is_rotating: bool
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            tap_max_duration: Duration::from_millis(300),
+            tap_max_distance: 10.0,
+            double_tap_max_interval: Duration::from_millis(300),
+            long_press_duration: Duration::from_millis(500),
+            pan_min_distance: 10.0,
+            pinch_min_scale: 0.05,
+            rotate_min_angle: 0.1
+        }
+    }
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        GestureRecognizer {
+            config,
+            windows: HashMap::new()
+        }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    /// Tracks touch and touchpad pressure events which happened at `now`, returning the gestures they completed.
+    /// Also returns long presses which are due, like [GestureRecognizer::poll].
+    pub fn handle_event<T>(&mut self, event: &Event<T>, now: Instant) -> Vec<GestureEvent> {
+        let mut gestures = self.poll(now);
+        if let Event::WindowEvent { window_id, event } = event {
+            let window_id = *window_id;
+            let mut push = |gesture| gestures.push(GestureEvent { window_id, gesture });
+            match event {
+                WindowEvent::Touch(touch) => {
+                    let window = self.windows.entry(window_id).or_default();
+                    window.handle_touch(&self.config, touch, now, &mut push);
+                }
+                WindowEvent::TouchpadPressure { pressure, stage, .. } => {
+                    let window = self.windows.entry(window_id).or_default();
+                    if *stage > window.pressure_stage {
+                        push(Gesture::ForcePress { stage: *stage, pressure: *pressure });
+                    }
+                    window.pressure_stage = *stage;
+                }
+                WindowEvent::Destroyed => drop(self.windows.remove(&window_id)),
+                _ => {}
+            }
+        }
+        gestures
+    }
+
+    /// Returns the long presses which are due at `now`
+    pub fn poll(&mut self, now: Instant) -> Vec<GestureEvent> {
+        let mut gestures = Vec::new();
+        for (window_id, window) in &mut self.windows {
+            if window.max_touches != 1 {
+                continue
+            }
+            for touch in &mut window.touches {
+                if !touch.is_used && now.saturating_duration_since(touch.start_time) >= self.config.long_press_duration {
+                    touch.is_used = true;
+                    gestures.push(GestureEvent { window_id: *window_id, gesture: Gesture::LongPress { position: touch.position } });
+                }
+            }
+        }
+        gestures
+    }
+
+    /// When the next long press will be due if the touches stay still, if any
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.windows.values()
+            .filter(|window| window.max_touches == 1)
+            .flat_map(|window| window.touches.iter())
+            .filter(|touch| !touch.is_used)
+            .map(|touch| touch.start_time + self.config.long_press_duration)
+            .min()
+    }
+}
+
+impl WindowGestures {
+    fn handle_touch(&mut self, config: &GestureConfig, touch: &Touch, now: Instant, push: &mut impl FnMut(Gesture)) {
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.push(TrackedTouch {
+                    id: touch.id,
+                    start: touch.location,
+                    start_time: now,
+                    position: touch.location,
+                    is_used: false
+                });
+                self.max_touches = self.max_touches.max(self.touches.len());
+                if self.touches.len() == 2 {
+                    // A second touch turns a pan into a pinch or rotation
+                    self.end_pan(GesturePhase::Ended, push);
+                    let (distance, angle) = self.two_touch_geometry();
+                    self.transform = Some(Transform {
+                        initial_distance: distance,
+                        initial_angle: angle,
+                        last_distance: distance,
+                        last_angle: angle,
+                        is_pinching: false,
+                        is_rotating: false
+                    });
+                }
+            }
+            TouchPhase::Moved => {
+                let tracked = match self.touches.iter_mut().find(|tracked| tracked.id == touch.id) {
+                    None => return,
+                    Some(tracked) => tracked
+                };
+                tracked.position = touch.location;
+                let tracked = *tracked;
+                let distance = distance(tracked.start, tracked.position);
+                if distance > config.tap_max_distance {
+                    self.touches.iter_mut().for_each(|tracked| tracked.is_used = true);
+                }
+
+                if self.touches.len() == 1 {
+                    self.move_pan(config, tracked, distance, push);
+                } else if self.touches.len() == 2 {
+                    self.move_transform(config, push);
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let idx = match self.touches.iter().position(|tracked| tracked.id == touch.id) {
+                    None => return,
+                    Some(idx) => idx
+                };
+                let tracked = self.touches.remove(idx);
+                let phase = if touch.phase == TouchPhase::Ended { GesturePhase::Ended } else { GesturePhase::Cancelled };
+
+                self.end_pan(phase, push);
+                if self.touches.len() < 2 {
+                    self.end_transform(phase, push);
+                }
+                if touch.phase == TouchPhase::Ended && self.max_touches == 1 && !tracked.is_used &&
+                    now.saturating_duration_since(tracked.start_time) <= config.tap_max_duration {
+                    self.tap(config, touch.location, now, push);
+                }
+                if self.touches.is_empty() {
+                    self.max_touches = 0;
+                }
+            }
+        }
+    }
+
+    fn tap(&mut self, config: &GestureConfig, position: PhysicalPosition<f64>, now: Instant, push: &mut impl FnMut(Gesture)) {
+        push(Gesture::Tap { position });
+        match self.last_tap.take() {
+            Some((last_time, last_position)) if now.saturating_duration_since(last_time) <= config.double_tap_max_interval &&
+                distance(last_position, position) <= config.tap_max_distance => {
+                push(Gesture::DoubleTap { position });
+            }
+            _ => self.last_tap = Some((now, position))
+        }
+    }
+
+    fn move_pan(&mut self, config: &GestureConfig, tracked: TrackedTouch, distance: f64, push: &mut impl FnMut(Gesture)) {
+        let translation = (tracked.position.x - tracked.start.x, tracked.position.y - tracked.start.y);
+        match &mut self.pan {
+            None => if distance > config.pan_min_distance {
+                self.pan = Some(Pan { start: tracked.start, last: tracked.position });
+                push(Gesture::Pan { phase: GesturePhase::Started, position: tracked.position, delta: translation, translation });
+            },
+            Some(pan) => {
+                let delta = (tracked.position.x - pan.last.x, tracked.position.y - pan.last.y);
+                pan.last = tracked.position;
+                push(Gesture::Pan { phase: GesturePhase::Changed, position: tracked.position, delta, translation });
+            }
+        }
+    }
+
+    fn end_pan(&mut self, phase: GesturePhase, push: &mut impl FnMut(Gesture)) {
+        if let Some(pan) = self.pan.take() {
+            let translation = (pan.last.x - pan.start.x, pan.last.y - pan.start.y);
+            push(Gesture::Pan { phase, position: pan.last, delta: (0.0, 0.0), translation });
+        }
+    }
+
+    fn move_transform(&mut self, config: &GestureConfig, push: &mut impl FnMut(Gesture)) {
+        let (distance, angle) = self.two_touch_geometry();
+        let center = self.two_touch_center();
+        let transform = match &mut self.transform {
+            None => return,
+            Some(transform) => transform
+        };
+
+        let scale = distance / transform.initial_distance.max(f64::EPSILON);
+        let delta_scale = distance / transform.last_distance.max(f64::EPSILON);
+        if transform.is_pinching {
+            push(Gesture::Pinch { phase: GesturePhase::Changed, center, scale, delta_scale });
+        } else if (scale - 1.0).abs() > config.pinch_min_scale {
+            transform.is_pinching = true;
+            push(Gesture::Pinch { phase: GesturePhase::Started, center, scale, delta_scale: scale });
+        }
+
+        let total_angle = normalize_angle(angle - transform.initial_angle);
+        let delta_angle = normalize_angle(angle - transform.last_angle);
+        if transform.is_rotating {
+            push(Gesture::Rotate { phase: GesturePhase::Changed, center, angle: total_angle, delta_angle });
+        } else if total_angle.abs() > config.rotate_min_angle {
+            transform.is_rotating = true;
+            push(Gesture::Rotate { phase: GesturePhase::Started, center, angle: total_angle, delta_angle: total_angle });
+        }
+
+        transform.last_distance = distance;
+        transform.last_angle = angle;
+    }
+
+    fn end_transform(&mut self, phase: GesturePhase, push: &mut impl FnMut(Gesture)) {
+        if let Some(transform) = self.transform.take() {
+            let center = self.touches.first().map_or(PhysicalPosition::new(0.0, 0.0), |tracked| tracked.position);
+            if transform.is_pinching {
+                let scale = transform.last_distance / transform.initial_distance.max(f64::EPSILON);
+                push(Gesture::Pinch { phase, center, scale, delta_scale: 1.0 });
+            }
+            if transform.is_rotating {
+                let angle = normalize_angle(transform.last_angle - transform.initial_angle);
+                push(Gesture::Rotate { phase, center, angle, delta_angle: 0.0 });
+            }
+        }
+    }
+
+    /// Distance and angle between the first two touches
+    fn two_touch_geometry(&self) -> (f64, f64) {
+        let (a, b) = (self.touches[0].position, self.touches[1].position);
+        (distance(a, b), (b.y - a.y).atan2(b.x - a.x))
+    }
+
+    fn two_touch_center(&self) -> PhysicalPosition<f64> {
+        let (a, b) = (self.touches[0].position, self.touches[1].position);
+        PhysicalPosition::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0)
+    }
+}
+
+fn distance(a: PhysicalPosition<f64>, b: PhysicalPosition<f64>) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+/// The same angle between -π and π
+fn normalize_angle(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(2.0 * PI);
+    if angle > PI { angle - 2.0 * PI } else { angle }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{Touch, TouchPhase};
+    use crate::event::{Event, WindowEvent};
+    use crate::test_support::{device_id, window_id};
+    use super::{Gesture, GestureConfig, GesturePhase, GestureRecognizer};
+
+    /// Feeds synthetic touches with times relative to `start`
+    struct Touches {
+        recognizer: GestureRecognizer,
+        start: Instant
+    }
+
+    impl Touches {
+        fn new() -> Self {
+            Touches { recognizer: GestureRecognizer::new(GestureConfig::default()), start: Instant::now() }
+        }
+
+        fn at(&self, millis: u64) -> Instant {
+            self.start + Duration::from_millis(millis)
+        }
+
+        fn touch(&mut self, millis: u64, id: u64, phase: TouchPhase, x: f64, y: f64) -> Vec<Gesture> {
+            let touch = Touch { device_id: device_id(), phase, location: PhysicalPosition::new(x, y), force: None, id };
+            let event: Event = Event::WindowEvent { window_id: window_id(), event: WindowEvent::Touch(touch) };
+            let now = self.at(millis);
+            self.recognizer.handle_event(&event, now).into_iter().map(|gesture| gesture.gesture).collect()
+        }
+
+        fn poll(&mut self, millis: u64) -> Vec<Gesture> {
+            let now = self.at(millis);
+            self.recognizer.poll(now).into_iter().map(|gesture| gesture.gesture).collect()
+        }
+    }
+
+    fn phases(gestures: &[Gesture]) -> Vec<(&'static str, GesturePhase)> {
+        gestures.iter().filter_map(|gesture| match gesture {
+            Gesture::Pan { phase, .. } => Some(("pan", *phase)),
+            Gesture::Pinch { phase, .. } => Some(("pinch", *phase)),
+            Gesture::Rotate { phase, .. } => Some(("rotate", *phase)),
+            _ => None
+        }).collect()
+    }
+
+    #[test]
+    fn tap() {
+        let mut touches = Touches::new();
+        assert_eq!(touches.touch(0, 0, TouchPhase::Started, 10.0, 10.0), []);
+        assert_eq!(touches.touch(100, 0, TouchPhase::Ended, 12.0, 10.0), [Gesture::Tap { position: PhysicalPosition::new(12.0, 10.0) }]);
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut touches = Touches::new();
+        touches.touch(0, 0, TouchPhase::Started, 10.0, 10.0);
+        touches.touch(50, 0, TouchPhase::Ended, 10.0, 10.0);
+        touches.touch(150, 1, TouchPhase::Started, 10.0, 10.0);
+        let position = PhysicalPosition::new(10.0, 10.0);
+        assert_eq!(touches.touch(200, 1, TouchPhase::Ended, 10.0, 10.0), [Gesture::Tap { position }, Gesture::DoubleTap { position }]);
+    }
+
+    #[test]
+    fn long_press() {
+        let mut touches = Touches::new();
+        touches.touch(0, 0, TouchPhase::Started, 10.0, 10.0);
+        assert_eq!(touches.recognizer.next_deadline(), Some(touches.at(500)));
+        assert_eq!(touches.poll(400), []);
+        assert_eq!(touches.poll(500), [Gesture::LongPress { position: PhysicalPosition::new(10.0, 10.0) }]);
+        assert_eq!(touches.recognizer.next_deadline(), None);
+        // Not also a tap
+        assert_eq!(touches.touch(600, 0, TouchPhase::Ended, 10.0, 10.0), []);
+    }
+
+    #[test]
+    fn pan() {
+        let mut touches = Touches::new();
+        touches.touch(0, 0, TouchPhase::Started, 0.0, 0.0);
+        assert_eq!(touches.touch(10, 0, TouchPhase::Moved, 5.0, 0.0), []);
+        let started = touches.touch(20, 0, TouchPhase::Moved, 20.0, 0.0);
+        assert_eq!(phases(&started), [("pan", GesturePhase::Started)]);
+        let changed = touches.touch(30, 0, TouchPhase::Moved, 30.0, 5.0);
+        assert!(matches!(changed[..], [Gesture::Pan { phase: GesturePhase::Changed, delta: (10.0, 5.0), translation: (30.0, 5.0), .. }]), "{:?}", changed);
+        assert_eq!(phases(&touches.touch(40, 0, TouchPhase::Ended, 30.0, 5.0)), [("pan", GesturePhase::Ended)]);
+    }
+
+    #[test]
+    fn pinch() {
+        let mut touches = Touches::new();
+        touches.touch(0, 0, TouchPhase::Started, 0.0, 0.0);
+        touches.touch(0, 1, TouchPhase::Started, 100.0, 0.0);
+        let started = touches.touch(10, 1, TouchPhase::Moved, 200.0, 0.0);
+        assert!(matches!(started[..], [Gesture::Pinch { phase: GesturePhase::Started, scale, .. }] if (scale - 2.0).abs() < 1e-9), "{:?}", started);
+        assert_eq!(phases(&touches.touch(20, 1, TouchPhase::Moved, 300.0, 0.0)), [("pinch", GesturePhase::Changed)]);
+        assert_eq!(phases(&touches.touch(30, 1, TouchPhase::Ended, 300.0, 0.0)), [("pinch", GesturePhase::Ended)]);
+    }
+
+    #[test]
+    fn rotate() {
+        let mut touches = Touches::new();
+        touches.touch(0, 0, TouchPhase::Started, 0.0, 0.0);
+        touches.touch(0, 1, TouchPhase::Started, 100.0, 0.0);
+        let started = touches.touch(10, 1, TouchPhase::Moved, 0.0, 100.0);
+        assert!(matches!(started[..], [Gesture::Rotate { phase: GesturePhase::Started, angle, .. }] if (angle - std::f64::consts::FRAC_PI_2).abs() < 1e-9), "{:?}", started);
+        assert_eq!(phases(&touches.touch(20, 1, TouchPhase::Moved, -100.0, 0.0)), [("rotate", GesturePhase::Changed)]);
+        assert_eq!(phases(&touches.touch(30, 0, TouchPhase::Cancelled, 0.0, 0.0)), [("rotate", GesturePhase::Cancelled)]);
+    }
+}
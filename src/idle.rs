@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+use winit::event::DeviceEvent;
+use crate::event::{Event, WindowEvent};
+
+/// User event broadcast to every proxy when the user goes idle or becomes active again,
+/// see [set_idle_thresholds](crate::set_idle_thresholds).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdleEvent {
+    /// There was no input for `threshold`. Sent once for each threshold, in increasing order
+    Idle {
+        threshold: Duration,
+        /// When the last input was
+        last_input: Instant
+    },
+    /// There was input after at least one [IdleEvent::Idle]
+    Active {
+        /// How long there was no input
+        idle_for: Duration
+    }
+}
+
+/// Watches for keyboard, mouse, touch and device input in the main loop, and reports when the time
+/// since the last input crosses each threshold.
+#[derive(Debug, Clone)]
+pub(crate) struct IdleDetector {
+    /// Sorted and deduplicated
+    thresholds: Vec<Duration>,
+    last_input: Instant,
+    /// How many thresholds were crossed since the last input
+    crossed: usize
+}
+
+impl IdleDetector {
+    pub(crate) fn new(now: Instant) -> Self {
+        IdleDetector {
+            thresholds: Vec::new(),
+            last_input: now,
+            crossed: 0
+        }
+    }
+
+    /// Replaces the thresholds, which must be sorted and deduplicated.
+    /// Thresholds which already passed are reported by the next [IdleDetector::poll]
+    pub(crate) fn set_thresholds(&mut self, thresholds: Vec<Duration>) {
+        debug_assert!(thresholds.windows(2).all(|pair| pair[0] < pair[1]), "idle thresholds must be sorted and deduplicated");
+        self.thresholds = thresholds;
+        self.crossed = 0;
+    }
+
+    /// Records input, returning [IdleEvent::Active] if the user was idle
+    pub(crate) fn handle_event(&mut self, event: &Event, now: Instant) -> Option<IdleEvent> {
+        if !is_input(event) {
+            return None
+        }
+        let idle_for = now.saturating_duration_since(self.last_input);
+        let was_idle = self.crossed > 0;
+        self.last_input = now;
+        self.crossed = 0;
+        was_idle.then_some(IdleEvent::Active { idle_for })
+    }
+
+    /// Returns [IdleEvent::Idle] for every threshold which passed since the last poll
+    pub(crate) fn poll(&mut self, now: Instant) -> Vec<IdleEvent> {
+        let idle_for = now.saturating_duration_since(self.last_input);
+        let crossed = self.thresholds.iter().take_while(|threshold| **threshold <= idle_for).count();
+        let events = self.thresholds[self.crossed.min(crossed)..crossed].iter()
+            .map(|threshold| IdleEvent::Idle { threshold: *threshold, last_input: self.last_input })
+            .collect();
+        self.crossed = self.crossed.max(crossed);
+        events
+    }
+
+    /// When the next threshold passes, so the main loop can wake up for it
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.thresholds.get(self.crossed).map(|threshold| self.last_input + *threshold)
+    }
+}
+
+fn is_input(event: &Event) -> bool {
+    match event {
+        Event::WindowEvent { event, .. } => matches!(event,
+            WindowEvent::KeyboardInput { is_synthetic: false, .. } |
+            WindowEvent::ReceivedCharacter(_) |
+            WindowEvent::CursorMoved { .. } |
+            WindowEvent::MouseWheel { .. } |
+            WindowEvent::MouseInput { .. } |
+            WindowEvent::TouchpadPressure { .. } |
+            WindowEvent::AxisMotion { .. } |
+            WindowEvent::Touch(_)
+        ),
+        Event::DeviceEvent { event, .. } => !matches!(event, DeviceEvent::Added | DeviceEvent::Removed),
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use winit::event::DeviceEvent;
+    use crate::event::Event;
+    use crate::test_support::device_id;
+    use super::{IdleDetector, IdleEvent};
+
+    #[test]
+    fn reports_each_threshold_once() {
+        let start = Instant::now();
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        let mut detector = IdleDetector::new(start);
+        detector.set_thresholds(vec![minutes(5), minutes(10)]);
+        assert_eq!(detector.deadline(), Some(start + minutes(5)));
+        assert_eq!(detector.poll(start + minutes(6)), [IdleEvent::Idle { threshold: minutes(5), last_input: start }]);
+        assert_eq!(detector.poll(start + minutes(7)), []);
+        assert_eq!(detector.deadline(), Some(start + minutes(10)));
+        assert_eq!(detector.poll(start + minutes(11)), [IdleEvent::Idle { threshold: minutes(10), last_input: start }]);
+        assert_eq!(detector.deadline(), None);
+
+        let input = Event::DeviceEvent { device_id: device_id(), event: DeviceEvent::MouseMotion { delta: (1.0, 0.0) } };
+        assert_eq!(detector.handle_event(&input, start + minutes(12)), Some(IdleEvent::Active { idle_for: minutes(12) }));
+        assert_eq!(detector.deadline(), Some(start + minutes(17)));
+    }
+}
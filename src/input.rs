@@ -0,0 +1,438 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ButtonId, DeviceEvent, DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, ScanCode, Touch, TouchPhase, VirtualKeyCode};
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+
+/// Keyboard, mouse and touch state per window and per device, folded from events via [InputState::handle_event].
+///
+/// "Pressed" and "released" queries are edges: they are true from the event until [InputState::end_frame],
+/// which should be called after handling [Event::MainEventsCleared]. "Held" queries are levels.
+///
+/// Proxies can maintain one automatically, see [EventLoop::track_input](crate::event_loop::EventLoop::track_input).
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    windows: HashMap<WindowId, WindowInput>,
+    devices: HashMap<DeviceId, DeviceInput>
+}
+
+/// Input to one window, from its [WindowEvent]s.
+#[derive(Debug, Clone, Default)]
+pub struct WindowInput {
+    /// From [WindowEvent::ModifiersChanged]
+    pub modifiers: ModifiersState,
+    /// From [WindowEvent::CursorMoved], `None` once the cursor leaves
+    pub cursor_position: Option<PhysicalPosition<f64>>,
+    keyboard: Keyboard,
+    mouse_buttons: Buttons<MouseButton>,
+    touches: HashMap<u64, Touch>
+}
+
+/// Input from one device, from its [DeviceEvent]s and the [WindowEvent]s it caused.
+///
+/// When a window loses focus, the keys and mouse buttons from its events are released here too,
+/// so raw [DeviceEvent::Key] presses may be released early.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInput {
+    /// Sum of [DeviceEvent::MouseMotion] deltas this frame
+    pub mouse_motion: (f64, f64),
+    /// The window the device's cursor is in and its position, from [WindowEvent::CursorMoved].
+    /// `None` once the cursor leaves
+    pub cursor_position: Option<(WindowId, PhysicalPosition<f64>)>,
+    keyboard: Keyboard,
+    mouse_buttons: Buttons<MouseButton>,
+    /// With the window each is in
+    touches: HashMap<u64, (WindowId, Touch)>,
+    buttons: Buttons<ButtonId>
+}
+
+/// Keys by [VirtualKeyCode] and by [ScanCode], for windows and devices
+#[derive(Debug, Clone, Default)]
+struct Keyboard {
+    keys: Buttons<VirtualKeyCode>,
+    scancodes: Buttons<ScanCode>
+}
+
+/// Held buttons, and the ones pressed and released this frame
+#[derive(Debug, Clone)]
+pub(crate) struct Buttons<K> {
+    pub(crate) held: HashSet<K>,
+    pub(crate) pressed: HashSet<K>,
+    pub(crate) released: HashSet<K>
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        InputState::default()
+    }
+
+    /// Updates the window or device the event is for.
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { window_id, event } => {
+                if let WindowEvent::Destroyed = event {
+                    self.windows.remove(window_id);
+                    for device in self.devices.values_mut() {
+                        device.forget_window(*window_id);
+                    }
+                    return
+                }
+                let window = self.windows.entry(*window_id).or_default();
+                match event {
+                    WindowEvent::KeyboardInput { device_id, input, .. } => {
+                        window.keyboard.handle_input(input);
+                        self.devices.entry(*device_id).or_default().keyboard.handle_input(input);
+                    }
+                    WindowEvent::ModifiersChanged(modifiers) => window.modifiers = *modifiers,
+                    WindowEvent::MouseInput { device_id, state, button, .. } => {
+                        window.mouse_buttons.set(*button, *state);
+                        self.devices.entry(*device_id).or_default().mouse_buttons.set(*button, *state);
+                    }
+                    WindowEvent::CursorMoved { device_id, position, .. } => {
+                        window.cursor_position = Some(*position);
+                        self.devices.entry(*device_id).or_default().cursor_position = Some((*window_id, *position));
+                    }
+                    WindowEvent::CursorLeft { device_id } => {
+                        window.cursor_position = None;
+                        self.devices.entry(*device_id).or_default().cursor_position = None;
+                    }
+                    WindowEvent::Touch(touch) => {
+                        let device = self.devices.entry(touch.device_id).or_default();
+                        match touch.phase {
+                            TouchPhase::Started | TouchPhase::Moved => {
+                                window.touches.insert(touch.id, *touch);
+                                device.touches.insert(touch.id, (*window_id, *touch));
+                            }
+                            TouchPhase::Ended | TouchPhase::Cancelled => {
+                                window.touches.remove(&touch.id);
+                                device.touches.remove(&touch.id);
+                            }
+                        }
+                    }
+                    // Releases won't arrive, so release everything to avoid stuck keys
+                    WindowEvent::Focused(false) => {
+                        window.release_all();
+                        for device in self.devices.values_mut() {
+                            device.release_window(*window_id);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::DeviceEvent { device_id, event } => {
+                let device = self.devices.entry(*device_id).or_default();
+                match event {
+                    DeviceEvent::Key(input) => device.keyboard.handle_input(input),
+                    DeviceEvent::Button { button, state } => device.buttons.set(*button, *state),
+                    DeviceEvent::MouseMotion { delta: (x, y) } => {
+                        device.mouse_motion.0 += x;
+                        device.mouse_motion.1 += y;
+                    }
+                    DeviceEvent::Removed => drop(self.devices.remove(device_id)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Resets the "pressed" and "released" edges and [DeviceInput::mouse_motion], starting the next frame.
+    pub fn end_frame(&mut self) {
+        for window in self.windows.values_mut() {
+            window.keyboard.end_frame();
+            window.mouse_buttons.end_frame();
+        }
+        for device in self.devices.values_mut() {
+            device.mouse_motion = (0.0, 0.0);
+            device.keyboard.end_frame();
+            device.mouse_buttons.end_frame();
+            device.buttons.end_frame();
+        }
+    }
+
+    /// Input to the window, or `None` if it had no events yet
+    pub fn window(&self, window_id: WindowId) -> Option<&WindowInput> {
+        self.windows.get(&window_id)
+    }
+
+    /// Input from the device, or `None` if it had no events yet
+    pub fn device(&self, device_id: DeviceId) -> Option<&DeviceInput> {
+        self.devices.get(&device_id)
+    }
+
+    /// Whether the key is held in any window
+    pub fn is_key_held(&self, key: VirtualKeyCode) -> bool {
+        self.windows.values().any(|window| window.is_key_held(key))
+    }
+
+    /// Whether the key was pressed this frame in any window
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.windows.values().any(|window| window.was_key_pressed(key))
+    }
+}
+
+impl WindowInput {
+    pub fn is_key_held(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.keys.held.contains(&key)
+    }
+
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.keys.pressed.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.keys.released.contains(&key)
+    }
+
+    pub fn held_keys(&self) -> impl Iterator<Item=VirtualKeyCode> + '_ {
+        self.keyboard.keys.held.iter().copied()
+    }
+
+    pub fn is_scancode_held(&self, scancode: ScanCode) -> bool {
+        self.keyboard.scancodes.held.contains(&scancode)
+    }
+
+    pub fn was_scancode_pressed(&self, scancode: ScanCode) -> bool {
+        self.keyboard.scancodes.pressed.contains(&scancode)
+    }
+
+    pub fn was_scancode_released(&self, scancode: ScanCode) -> bool {
+        self.keyboard.scancodes.released.contains(&scancode)
+    }
+
+    pub fn is_mouse_button_held(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.held.contains(&button)
+    }
+
+    pub fn was_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed.contains(&button)
+    }
+
+    pub fn was_mouse_button_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.released.contains(&button)
+    }
+
+    /// Touches which started and haven't ended, with their latest location
+    pub fn touches(&self) -> impl Iterator<Item=&Touch> + '_ {
+        self.touches.values()
+    }
+
+    fn release_all(&mut self) {
+        self.keyboard.release_all();
+        self.mouse_buttons.release_all();
+        self.touches.clear();
+    }
+}
+
+impl DeviceInput {
+    pub fn is_key_held(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.keys.held.contains(&key)
+    }
+
+    pub fn was_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.keys.pressed.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keyboard.keys.released.contains(&key)
+    }
+
+    pub fn is_scancode_held(&self, scancode: ScanCode) -> bool {
+        self.keyboard.scancodes.held.contains(&scancode)
+    }
+
+    /// Whether the mouse button (from [WindowEvent::MouseInput] in any window) is held
+    pub fn is_mouse_button_held(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.held.contains(&button)
+    }
+
+    pub fn was_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed.contains(&button)
+    }
+
+    pub fn was_mouse_button_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.released.contains(&button)
+    }
+
+    /// The device's touches which started and haven't ended, with the window each is in
+    pub fn touches(&self) -> impl Iterator<Item=(WindowId, &Touch)> + '_ {
+        self.touches.values().map(|(window_id, touch)| (*window_id, touch))
+    }
+
+    /// Whether the raw button (from [DeviceEvent::Button]) is held
+    pub fn is_button_held(&self, button: ButtonId) -> bool {
+        self.buttons.held.contains(&button)
+    }
+
+    pub fn was_button_pressed(&self, button: ButtonId) -> bool {
+        self.buttons.pressed.contains(&button)
+    }
+
+    pub fn was_button_released(&self, button: ButtonId) -> bool {
+        self.buttons.released.contains(&button)
+    }
+
+    /// Releases what the window's events pressed. Raw buttons aren't, they don't depend on focus
+    fn release_window(&mut self, window_id: WindowId) {
+        self.keyboard.release_all();
+        self.mouse_buttons.release_all();
+        self.touches.retain(|_, (touch_window_id, _)| *touch_window_id != window_id);
+    }
+
+    fn forget_window(&mut self, window_id: WindowId) {
+        if self.cursor_position.is_some_and(|(cursor_window_id, _)| cursor_window_id == window_id) {
+            self.cursor_position = None;
+        }
+        self.touches.retain(|_, (touch_window_id, _)| *touch_window_id != window_id);
+    }
+}
+
+impl Keyboard {
+    fn handle_input(&mut self, input: &KeyboardInput) {
+        self.scancodes.set(input.scancode, input.state);
+        if let Some(key) = input.virtual_keycode {
+            self.keys.set(key, input.state);
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.keys.release_all();
+        self.scancodes.release_all();
+    }
+
+    fn end_frame(&mut self) {
+        self.keys.end_frame();
+        self.scancodes.end_frame();
+    }
+}
+
+impl<K: Copy + Eq + Hash> Buttons<K> {
+    pub(crate) fn set(&mut self, button: K, state: ElementState) {
+        // Key repeats and the same key from window and device events aren't new presses
+        match state {
+            ElementState::Pressed => if self.held.insert(button) {
+                self.pressed.insert(button);
+            },
+            ElementState::Released => if self.held.remove(&button) {
+                self.released.insert(button);
+            }
+        }
+    }
+
+    pub(crate) fn release_all(&mut self) {
+        self.released.extend(self.held.drain());
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+impl<K> Default for Buttons<K> {
+    fn default() -> Self {
+        Buttons {
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new()
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, Touch, TouchPhase, VirtualKeyCode};
+    use crate::event::{Event, WindowEvent};
+    use crate::test_support::{device_id, window_id};
+    use super::InputState;
+
+    fn window_event(event: WindowEvent) -> Event {
+        Event::WindowEvent { window_id: window_id(), event }
+    }
+
+    fn key(state: ElementState) -> Event {
+        let input = KeyboardInput { scancode: 30, state, virtual_keycode: Some(VirtualKeyCode::A), modifiers: ModifiersState::empty() };
+        window_event(WindowEvent::KeyboardInput { device_id: device_id(), input, is_synthetic: false })
+    }
+
+    fn mouse(state: ElementState) -> Event {
+        window_event(WindowEvent::MouseInput { device_id: device_id(), state, button: MouseButton::Left, modifiers: ModifiersState::empty() })
+    }
+
+    fn touch(phase: TouchPhase) -> Event {
+        window_event(WindowEvent::Touch(Touch { device_id: device_id(), phase, location: PhysicalPosition::new(1.0, 2.0), force: None, id: 7 }))
+    }
+
+    #[test]
+    fn pressed_and_released_edges() {
+        let mut input = InputState::new();
+        input.handle_event(&key(ElementState::Pressed));
+        let window = input.window(window_id()).unwrap();
+        assert!(window.is_key_held(VirtualKeyCode::A) && window.was_key_pressed(VirtualKeyCode::A) && window.was_scancode_pressed(30));
+        assert!(input.device(device_id()).unwrap().was_key_pressed(VirtualKeyCode::A));
+
+        // Held, but the edge is over
+        input.end_frame();
+        // A repeat isn't a new press
+        input.handle_event(&key(ElementState::Pressed));
+        let window = input.window(window_id()).unwrap();
+        assert!(window.is_key_held(VirtualKeyCode::A) && !window.was_key_pressed(VirtualKeyCode::A));
+
+        input.handle_event(&key(ElementState::Released));
+        let window = input.window(window_id()).unwrap();
+        assert!(!window.is_key_held(VirtualKeyCode::A) && window.was_key_released(VirtualKeyCode::A));
+        assert!(input.device(device_id()).unwrap().was_key_released(VirtualKeyCode::A));
+
+        input.end_frame();
+        assert!(!input.window(window_id()).unwrap().was_key_released(VirtualKeyCode::A));
+    }
+
+    #[test]
+    fn tracks_devices() {
+        let mut input = InputState::new();
+        let position = PhysicalPosition::new(3.0, 4.0);
+        input.handle_event(&window_event(WindowEvent::CursorMoved { device_id: device_id(), position, modifiers: ModifiersState::empty() }));
+        input.handle_event(&mouse(ElementState::Pressed));
+        input.handle_event(&touch(TouchPhase::Started));
+
+        let device = input.device(device_id()).unwrap();
+        assert_eq!(device.cursor_position, Some((window_id(), position)));
+        assert!(device.is_mouse_button_held(MouseButton::Left) && device.was_mouse_button_pressed(MouseButton::Left));
+        assert_eq!(device.touches().map(|(window_id, touch)| (window_id, touch.id)).collect::<Vec<_>>(), [(window_id(), 7)]);
+
+        input.handle_event(&mouse(ElementState::Released));
+        input.handle_event(&touch(TouchPhase::Ended));
+        input.handle_event(&window_event(WindowEvent::CursorLeft { device_id: device_id() }));
+        let device = input.device(device_id()).unwrap();
+        assert!(!device.is_mouse_button_held(MouseButton::Left) && device.was_mouse_button_released(MouseButton::Left));
+        assert_eq!((device.cursor_position, device.touches().count()), (None, 0));
+    }
+
+    #[test]
+    fn focus_loss_releases_everything() {
+        let mut input = InputState::new();
+        input.handle_event(&key(ElementState::Pressed));
+        input.handle_event(&mouse(ElementState::Pressed));
+        input.handle_event(&touch(TouchPhase::Started));
+        input.end_frame();
+
+        input.handle_event(&window_event(WindowEvent::Focused(false)));
+        let window = input.window(window_id()).unwrap();
+        assert!(!window.is_key_held(VirtualKeyCode::A) && window.was_key_released(VirtualKeyCode::A));
+        assert!(!window.is_scancode_held(30) && window.was_scancode_released(30));
+        assert!(!window.is_mouse_button_held(MouseButton::Left) && window.was_mouse_button_released(MouseButton::Left));
+        assert_eq!(window.touches().count(), 0);
+        let device = input.device(device_id()).unwrap();
+        assert!(!device.is_key_held(VirtualKeyCode::A) && device.was_key_released(VirtualKeyCode::A));
+        assert!(!device.is_mouse_button_held(MouseButton::Left));
+        assert_eq!(device.touches().count(), 0);
+
+        // The release which arrives after refocusing isn't another edge
+        input.end_frame();
+        input.handle_event(&key(ElementState::Released));
+        assert!(!input.window(window_id()).unwrap().was_key_released(VirtualKeyCode::A));
+    }
+}
@@ -1,5 +1,8 @@
 #![doc = include_str!("../README.md")]
 
+/// Named input actions and axes, with bindings loaded from config files.
+#[cfg(feature = "actions")]
+pub mod actions;
 /// Proxy event loops and control flow type.
 pub mod event_loop;
 /// Events received by the proxy event loops.
@@ -7,9 +10,38 @@ pub mod event;
 /// Futures, since most of the operations are across threads.
 #[doc(hidden)]
 pub mod future;
+/// Grouped drag-and-drop file events.
+pub mod file_drop;
+/// Per-window frame timing statistics.
+pub mod frame_stats;
+/// Fixed-timestep game loop driver.
+pub mod game_loop;
+/// Mouse clicks with counts, drags and hover.
+pub mod pointer;
+/// Redraw scheduling and per-window frame clocks.
+pub mod redraw;
+/// Touch gesture recognition.
+pub mod gesture;
+/// User idle detection.
+pub mod idle;
+/// Keyboard, mouse and touch state tracking.
+pub mod input;
+/// Scroll delta normalization.
+pub mod scroll;
+/// Keyboard shortcuts and chords.
+pub mod shortcut;
+/// Text editing commands from keyboard events.
+pub mod text_input;
+/// Async timers which are woken by the main event loop.
+pub mod timer;
+/// Per-window state mirrored from window events.
+pub mod window_state;
 /// Messages sent between the proxy event loops and shared event loop.
 mod messages;
 /// Function to initialize the main event loop for the proxies.
 mod run;
+/// Fixtures shared by unit tests.
+#[cfg(test)]
+mod test_support;
 
 pub use run::*;
\ No newline at end of file
@@ -1,54 +1,175 @@
-use std::any::Any;
-use winit::window::{Window, WindowBuilder};
+use std::any::{Any, TypeId};
+use winit::window::{Window, WindowBuilder, WindowId};
 use winit::error::OsError;
 use flume::{Receiver, Sender};
 use std::sync::{Arc, Weak};
 use crossbeam_utils::atomic::AtomicCell;
 use std::task::Waker;
-use crate::event::Event;
-use crate::event_loop::ControlFlow;
+use winit::event::StartCause;
+use crate::event::{Event, UserEvent};
+use crate::redraw::FrameClock;
+use crate::shortcut::{Shortcut, ShortcutConflict, ShortcutScope};
+use crate::timer::MainTimer;
+use crate::window_state::WindowSnapshot;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use crate::event_loop::{ControlFlow, EventMeta, ProxyId, RequestError};
 
 pub(crate) enum ProxyRequest {
     SpawnWindow {
         configure: Box<dyn FnOnce(WindowBuilder) -> WindowBuilder + Send>
     },
+    Snapshot,
+    RegisterShortcut { shortcut: Shortcut, scope: ShortcutScope },
     RunOnMainThread {
         action: Box<dyn FnOnce() -> Box<dyn Any> + Send>
+    },
+    /// Response is a [ProxyResponse::Call] with the same `call_id`, which may come out-of-order
+    Call {
+        call_id: u64,
+        target: ProxyId,
+        request: Box<dyn Any + Send>,
+        timeout: Option<Duration>
+    },
+    // These have no response
+    Subscribe { topic: String },
+    Unsubscribe { topic: String },
+    ScheduleRedraw { window_id: WindowId, window: Weak<Window> },
+    /// Response is a [ProxyResponse::Event] with a [WindowSnapshot] user event, in order with the other events
+    SnapshotEvent,
+    UnregisterShortcut { shortcut: Shortcut, scope: ShortcutScope },
+    /// Reply to a [ProxyResponse::Called]. `response` is `None` if there is no handler
+    Return {
+        call_id: u64,
+        response: Option<Box<dyn Any + Send>>
     }
 }
 
 pub(crate) enum ProxyResponse {
     SpawnWindow { result: Result<Window, OsError> },
     RunOnMainThread { return_value: Box<dyn Any> },
-    Event(Event)
+    Snapshot { snapshot: WindowSnapshot },
+    RegisterShortcut { result: Result<(), ShortcutConflict> },
+    /// Another proxy's request was answered
+    Call { call_id: u64, result: Result<Box<dyn Any + Send>, RequestError> },
+    /// Another proxy sent this proxy a request
+    Called { call_id: u64, request: Box<dyn Any + Send> },
+    /// Sent right before every [Event::RedrawRequested] for the same window
+    FrameClock { window_id: WindowId, clock: FrameClock },
+    Event(Event, EventMeta)
 }
 
-pub(crate) struct ProxyRegister(pub(crate) Weak<AtomicCell<ProxyRegisterBody>>);
+pub(crate) struct ProxyRegister {
+    pub(crate) body: Weak<AtomicCell<ProxyRegisterBody>>,
+    /// The only type of user events the proxy accepts, `None` if it accepts every [UserEvent]
+    pub(crate) user_event: Option<TypeId>
+}
 
+#[derive(Default)]
 pub(crate) enum ProxyRegisterBody {
+    #[default]
     Init,
     Polled { waker: Waker },
     Ready { info: ProxyRegisterInfo }
 }
 
-impl Default for ProxyRegisterBody {
-    fn default() -> Self {
-        ProxyRegisterBody::Init
-    }
-}
-
 pub(crate) struct ProxyRegisterInfo {
-    // pub(crate) id: ProxyId,
+    pub(crate) id: ProxyId,
     pub(crate) control_flow: Arc<AtomicCell<ControlFlow>>,
+    pub(crate) timer: Arc<AtomicCell<Option<Instant>>>,
     pub(crate) send: Sender<ProxyRequest>,
     pub(crate) recv: Receiver<ProxyResponse>
 }
 
 pub(crate) struct AppProxyRegisterInfo {
-    // pub(crate) id: ProxyId,
+    pub(crate) id: ProxyId,
     pub(crate) control_flow: Arc<AtomicCell<ControlFlow>>,
+    /// `control_flow` when the previous iteration ended, with [ControlFlow::Frame] resolved into [ControlFlow::WaitUntil]
+    pub(crate) requested_control_flow: ControlFlow,
+    pub(crate) timer: Arc<AtomicCell<Option<Instant>>>,
     pub(crate) recv_from_proxy: Receiver<ProxyRequest>,
     pub(crate) send_to_proxy: Sender<ProxyResponse>,
+    pub(crate) topics: HashSet<String>,
+    /// See [ProxyRegister::user_event]
+    pub(crate) user_event: Option<TypeId>
+}
+
+/// A request between proxies which the main loop is waiting on a reply for
+pub(crate) struct PendingCall {
+    pub(crate) caller: ProxyId,
+    pub(crate) caller_call_id: u64,
+    pub(crate) target: ProxyId,
+    pub(crate) deadline: Option<Instant>
+}
+
+/// The actual user event type of the winit event loop.
+#[derive(Debug)]
+pub(crate) enum MainEvent {
+    /// User event along with which proxies it's for
+    User { route: Route, event: UserEvent },
+    /// Makes the main loop run without an event, so it picks up changes like new timers
+    Wake,
+    /// Async timer (see [crate::timer]) to wake when its deadline passes
+    AddTimer(MainTimer)
+}
+
+#[derive(Debug)]
+pub(crate) enum Route {
+    /// Every proxy
+    Broadcast,
+    /// One proxy
+    Proxy(ProxyId),
+    /// Every proxy subscribed to the topic
+    Topic(String)
+}
+
+impl AppProxyRegisterInfo {
+    /// The [StartCause] of this proxy's own wait, which started at `start`
+    pub(crate) fn start_cause(&self, start: Instant, now: Instant) -> StartCause {
+        let timer = self.timer.load();
+        if let Some(timer) = timer.filter(|timer| *timer <= now) {
+            // Timers only fire once, unless the proxy already set another one
+            let _ = self.timer.compare_exchange(Some(timer), None);
+            return StartCause::ResumeTimeReached { start, requested_resume: timer };
+        }
+        let wait_until = match self.requested_control_flow {
+            ControlFlow::Poll => return StartCause::Poll,
+            ControlFlow::WaitUntil(instant) => Some(instant),
+            ControlFlow::Frame(_) => unreachable!("requested_control_flow has frames resolved into WaitUntil"),
+            ControlFlow::Wait | ControlFlow::ExitLocal | ControlFlow::ExitApp => None
+        };
+        match wait_until {
+            Some(instant) if instant <= now => StartCause::ResumeTimeReached { start, requested_resume: instant },
+            _ => StartCause::WaitCancelled { start, requested_resume: wait_until.into_iter().chain(timer).min() }
+        }
+    }
+}
+
+impl Route {
+    /// Whether the event goes to the proxy. User events also have to be the type the proxy accepts,
+    /// so they aren't cloned only to be discarded
+    pub(crate) fn includes(&self, proxy: &AppProxyRegisterInfo, event: &Event) -> bool {
+        if let (Event::UserEvent(user_event), Some(user_event_type)) = (event, proxy.user_event) {
+            if user_event.value_type_id() != user_event_type {
+                return false
+            }
+        }
+        match self {
+            Route::Broadcast => true,
+            Route::Proxy(id) => proxy.id == *id,
+            Route::Topic(topic) => proxy.topics.contains(topic)
+        }
+    }
 }
 
 pub(crate) static mut REGISTER_PROXY: Option<Sender<ProxyRegister>> = None;
+pub(crate) static mut MAIN_PROXY: Option<winit::event_loop::EventLoopProxy<MainEvent>> = None;
+
+pub(crate) fn main_proxy() -> winit::event_loop::EventLoopProxy<MainEvent> {
+    // SAFETY: This is already initialized and will only be read
+    unsafe {
+        (*std::ptr::addr_of!(MAIN_PROXY)).as_ref()
+            .expect("you must call winit_modular::run before using proxy event loops")
+            .clone()
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton};
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+
+/// Thresholds for [PointerTracker].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerConfig {
+    /// Longest time between clicks to count as a multi-click (e.g. double-click)
+    pub click_max_interval: Duration,
+    /// Farthest (in physical pixels) the cursor can move between clicks to count as a multi-click
+    pub click_max_distance: f64,
+    /// How far (in physical pixels) the cursor must move with a button held to start dragging.
+    /// Released buttons which didn't drag are clicks
+    pub drag_min_distance: f64
+}
+
+/// Higher-level mouse input, see [PointerTracker].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pointer {
+    /// A button was pressed and released without dragging. `count` is 1 for a single click, 2 for a double-click and so on
+    Click { button: MouseButton, position: PhysicalPosition<f64>, count: u32 },
+    /// A button was held and the cursor moved past [PointerConfig::drag_min_distance] from where it was pressed, `start`
+    DragStart { button: MouseButton, start: PhysicalPosition<f64>, position: PhysicalPosition<f64> },
+    /// The cursor moved while dragging. `delta` is from the previous drag event
+    DragMove { button: MouseButton, start: PhysicalPosition<f64>, position: PhysicalPosition<f64>, delta: (f64, f64) },
+    /// The button was released or the window lost focus
+    DragEnd { button: MouseButton, start: PhysicalPosition<f64>, position: PhysicalPosition<f64> },
+    /// The cursor entered the window
+    HoverEnter,
+    /// The cursor left the window
+    HoverLeave
+}
+
+/// A [Pointer] event in a window, returned by [PointerTracker::handle_event].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerEvent {
+    pub window_id: WindowId,
+    pub pointer: Pointer
+}
+
+/// Turns [WindowEvent::MouseInput], [WindowEvent::CursorMoved], [WindowEvent::CursorEntered] and
+/// [WindowEvent::CursorLeft] into clicks with a count, drags, and hover changes, per window and button.
+#[derive(Debug, Clone)]
+pub struct PointerTracker {
+    config: PointerConfig,
+    windows: HashMap<WindowId, WindowPointer>
+}
+
+#[derive(Debug, Clone, Default)]
+struct WindowPointer {
+    position: Option<PhysicalPosition<f64>>,
+    buttons: HashMap<MouseButton, ButtonTrack>
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ButtonTrack {
+    press: Option<Press>,
+    last_click: Option<Click>
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    /// Where the button was pressed. If the cursor position wasn't known yet (e.g. the click which focused
+    /// the window) it's the position of the next [WindowEvent::CursorMoved]
+    start: Option<PhysicalPosition<f64>>,
+    /// Last position while dragging, `None` if not dragging
+    drag_position: Option<PhysicalPosition<f64>>
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Click {
+    time: Instant,
+    position: PhysicalPosition<f64>,
+    count: u32
+}
+
+impl Default for PointerConfig {
+    fn default() -> Self {
+        PointerConfig {
+            click_max_interval: Duration::from_millis(500),
+            click_max_distance: 4.0,
+            drag_min_distance: 4.0
+        }
+    }
+}
+
+impl PointerTracker {
+    pub fn new(config: PointerConfig) -> Self {
+        PointerTracker {
+            config,
+            windows: HashMap::new()
+        }
+    }
+
+    pub fn config(&self) -> &PointerConfig {
+        &self.config
+    }
+
+    /// The last known cursor position in the window, which is kept after the cursor leaves.
+    /// `None` before the window's first [WindowEvent::CursorMoved]
+    pub fn position(&self, window_id: WindowId) -> Option<PhysicalPosition<f64>> {
+        self.windows.get(&window_id).and_then(|window| window.position)
+    }
+
+    /// Whether the button is dragging in the window
+    pub fn is_dragging(&self, window_id: WindowId, button: MouseButton) -> bool {
+        self.windows.get(&window_id)
+            .and_then(|window| window.buttons.get(&button))
+            .and_then(|track| track.press)
+            .is_some_and(|press| press.drag_position.is_some())
+    }
+
+    /// Tracks a mouse event which happened at `now`, returning the pointer events it caused
+    pub fn handle_event<T>(&mut self, event: &Event<T>, now: Instant) -> Vec<PointerEvent> {
+        let (window_id, event) = match event {
+            Event::WindowEvent { window_id, event } => (*window_id, event),
+            _ => return Vec::new()
+        };
+        let mut pointers = Vec::new();
+        let mut push = |pointer| pointers.push(PointerEvent { window_id, pointer });
+        if let WindowEvent::Destroyed = event {
+            self.windows.remove(&window_id);
+            return pointers
+        }
+        let window = self.windows.entry(window_id).or_default();
+        match event {
+            WindowEvent::CursorEntered { .. } => push(Pointer::HoverEnter),
+            WindowEvent::CursorLeft { .. } => push(Pointer::HoverLeave),
+            WindowEvent::CursorMoved { position, .. } => {
+                window.position = Some(*position);
+                for (button, track) in &mut window.buttons {
+                    if let Some(press) = &mut track.press {
+                        match (press.start, press.drag_position) {
+                            (None, _) => press.start = Some(*position),
+                            (Some(start), None) => if distance(start, *position) >= self.config.drag_min_distance {
+                                press.drag_position = Some(*position);
+                                push(Pointer::DragStart { button: *button, start, position: *position });
+                            },
+                            (Some(start), Some(last)) => {
+                                press.drag_position = Some(*position);
+                                let delta = (position.x - last.x, position.y - last.y);
+                                push(Pointer::DragMove { button: *button, start, position: *position, delta });
+                            }
+                        }
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                // Button events have no position, so use the last known one
+                let position = window.position;
+                let track = window.buttons.entry(*button).or_default();
+                match (state, track.press.take(), position) {
+                    (ElementState::Pressed, _, _) => track.press = Some(Press { start: position, drag_position: None }),
+                    // Nothing was pressed, or the cursor never moved so there's no position to click at
+                    (ElementState::Released, None, _) | (ElementState::Released, _, None) => {}
+                    (ElementState::Released, Some(press), Some(position)) => match press.drag_position {
+                        Some(_) => push(Pointer::DragEnd { button: *button, start: press.start.unwrap_or(position), position }),
+                        None => {
+                            let count = match track.last_click {
+                                Some(last) if now.saturating_duration_since(last.time) <= self.config.click_max_interval &&
+                                    distance(last.position, position) <= self.config.click_max_distance => last.count + 1,
+                                _ => 1
+                            };
+                            track.last_click = Some(Click { time: now, position, count });
+                            push(Pointer::Click { button: *button, position, count });
+                        }
+                    }
+                }
+            }
+            // The window won't see the button go up, so drags end here
+            WindowEvent::Focused(false) => {
+                for (button, track) in &mut window.buttons {
+                    if let Some(Press { start: Some(start), drag_position: Some(position) }) = track.press.take() {
+                        push(Pointer::DragEnd { button: *button, start, position });
+                    }
+                }
+            }
+            _ => {}
+        }
+        pointers
+    }
+}
+
+fn distance(a: PhysicalPosition<f64>, b: PhysicalPosition<f64>) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{ElementState, ModifiersState, MouseButton};
+    use crate::event::{Event, WindowEvent};
+    use crate::test_support::{device_id, window_id};
+    use super::{Pointer, PointerConfig, PointerTracker};
+
+    fn event(event: WindowEvent) -> Event {
+        Event::WindowEvent { window_id: window_id(), event }
+    }
+
+    fn moved(x: f64, y: f64) -> Event {
+        event(WindowEvent::CursorMoved { device_id: device_id(), position: PhysicalPosition::new(x, y), modifiers: ModifiersState::empty() })
+    }
+
+    fn button(state: ElementState) -> Event {
+        event(WindowEvent::MouseInput { device_id: device_id(), state, button: MouseButton::Left, modifiers: ModifiersState::empty() })
+    }
+
+    fn pointers(tracker: &mut PointerTracker, event: Event, now: Instant) -> Vec<Pointer> {
+        tracker.handle_event(&event, now).into_iter().map(|pointer| pointer.pointer).collect()
+    }
+
+    #[test]
+    fn counts_clicks() {
+        let mut tracker = PointerTracker::new(PointerConfig::default());
+        let start = Instant::now();
+        pointers(&mut tracker, moved(10.0, 10.0), start);
+        let mut counts = Vec::new();
+        for (i, offset) in [0, 100, 200, 2000].into_iter().enumerate() {
+            let now = start + Duration::from_millis(offset);
+            pointers(&mut tracker, button(ElementState::Pressed), now);
+            match pointers(&mut tracker, button(ElementState::Released), now)[..] {
+                [Pointer::Click { count, .. }] => counts.push(count),
+                ref other => panic!("click {} produced {:?}", i, other)
+            }
+        }
+        assert_eq!(counts, [1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn drags_past_threshold() {
+        let mut tracker = PointerTracker::new(PointerConfig::default());
+        let now = Instant::now();
+        pointers(&mut tracker, moved(10.0, 10.0), now);
+        pointers(&mut tracker, button(ElementState::Pressed), now);
+        assert_eq!(pointers(&mut tracker, moved(11.0, 10.0), now), []);
+        let start = PhysicalPosition::new(10.0, 10.0);
+        assert_eq!(pointers(&mut tracker, moved(30.0, 10.0), now), [Pointer::DragStart { button: MouseButton::Left, start, position: PhysicalPosition::new(30.0, 10.0) }]);
+        assert_eq!(pointers(&mut tracker, moved(40.0, 15.0), now), [Pointer::DragMove { button: MouseButton::Left, start, position: PhysicalPosition::new(40.0, 15.0), delta: (10.0, 5.0) }]);
+        assert_eq!(pointers(&mut tracker, button(ElementState::Released), now), [Pointer::DragEnd { button: MouseButton::Left, start, position: PhysicalPosition::new(40.0, 15.0) }]);
+    }
+
+    #[test]
+    fn press_before_position_starts_at_next_move() {
+        let mut tracker = PointerTracker::new(PointerConfig::default());
+        let now = Instant::now();
+        pointers(&mut tracker, button(ElementState::Pressed), now);
+        assert_eq!(pointers(&mut tracker, moved(500.0, 500.0), now), []);
+        assert_eq!(pointers(&mut tracker, button(ElementState::Released), now), [Pointer::Click { button: MouseButton::Left, position: PhysicalPosition::new(500.0, 500.0), count: 1 }]);
+    }
+
+    #[test]
+    fn keeps_position_after_cursor_leaves() {
+        let mut tracker = PointerTracker::new(PointerConfig::default());
+        let now = Instant::now();
+        pointers(&mut tracker, moved(100.0, 100.0), now);
+        assert_eq!(pointers(&mut tracker, event(WindowEvent::CursorLeft { device_id: device_id() }), now), [Pointer::HoverLeave]);
+        pointers(&mut tracker, button(ElementState::Pressed), now);
+        assert_eq!(pointers(&mut tracker, moved(101.0, 100.0), now), []);
+        assert_eq!(tracker.position(window_id()), Some(PhysicalPosition::new(101.0, 100.0)));
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::sync::Weak;
+use std::time::{Duration, Instant};
+use winit::window::{Window, WindowId};
+use crate::event_loop::next_frame;
+
+/// Timing of a window's current frame, sent by the main loop with each
+/// [Event::RedrawRequested](crate::event::Event::RedrawRequested), whether it was scheduled or requested by the OS.
+///
+/// Get it from [EventLoop::frame_clock](crate::event_loop::EventLoop::frame_clock).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameClock {
+    /// Number of redraws of this window before this one
+    pub frame: u64,
+    /// When the main loop received this redraw
+    pub time: Instant,
+    /// Time since the previous redraw of this window, or `None` on the first frame
+    pub previous_frame_duration: Option<Duration>,
+    /// When this redraw was first scheduled via [EventLoop::schedule_redraw](crate::event_loop::EventLoop::schedule_redraw),
+    /// or `None` if it wasn't scheduled (e.g. the OS requested it, or it was requested via [Window::request_redraw] directly)
+    pub scheduled: Option<Instant>
+}
+
+/// Main loop state for redraws: which windows are scheduled and their frame clocks.
+///
+/// `W` is how to request the redraw, generic so this works without windows.
+#[derive(Debug)]
+pub(crate) struct RedrawScheduler<W = Weak<Window>> {
+    scheduled: HashMap<WindowId, Scheduled<W>>,
+    clocks: HashMap<WindowId, FrameClock>,
+    next_redraw: Option<Instant>
+}
+
+#[derive(Debug)]
+struct Scheduled<W> {
+    /// `None` once the redraw is requested, until the window is redrawn
+    window: Option<W>,
+    /// When it was first scheduled
+    since: Instant
+}
+
+impl<W> Default for RedrawScheduler<W> {
+    fn default() -> Self {
+        RedrawScheduler {
+            scheduled: HashMap::new(),
+            clocks: HashMap::new(),
+            next_redraw: None
+        }
+    }
+}
+
+impl<W> RedrawScheduler<W> {
+    /// Marks the window to be redrawn in the next batch. Requests before it's redrawn are coalesced
+    pub(crate) fn schedule(&mut self, window_id: WindowId, window: W, now: Instant) {
+        // Request again even if it was already requested: winit coalesces it, and the window doesn't get stuck
+        // if a requested redraw never comes
+        self.scheduled.entry(window_id).or_insert(Scheduled { window: None, since: now }).window = Some(window);
+    }
+
+    /// Forgets a destroyed window
+    pub(crate) fn remove(&mut self, window_id: WindowId) {
+        self.scheduled.remove(&window_id);
+        self.clocks.remove(&window_id);
+    }
+
+    /// If any windows need a redraw requested and one is due at `rate` (or `rate` is `None`),
+    /// returns them to request. They stay scheduled until they're redrawn
+    pub(crate) fn take_due(&mut self, rate: Option<u32>, epoch: Instant, now: Instant) -> Vec<(WindowId, W)> {
+        if !self.has_unrequested() || self.next_redraw.is_some_and(|next_redraw| next_redraw > now) {
+            return Vec::new();
+        }
+        self.next_redraw = rate.map(|rate| next_frame(epoch, rate, now));
+        self.scheduled.iter_mut()
+            .filter_map(|(window_id, scheduled)| scheduled.window.take().map(|window| (*window_id, window)))
+            .collect()
+    }
+
+    /// Advances the window's frame clock for a [Event::RedrawRequested](crate::event::Event::RedrawRequested)
+    /// the main loop received at `now`. A scheduled redraw is done, even if the OS requested this one
+    pub(crate) fn redrawn(&mut self, window_id: WindowId, now: Instant) -> FrameClock {
+        let scheduled = self.scheduled.remove(&window_id).map(|scheduled| scheduled.since);
+        let clock = match self.clocks.get(&window_id) {
+            None => FrameClock { frame: 0, time: now, previous_frame_duration: None, scheduled },
+            Some(previous) => FrameClock {
+                frame: previous.frame + 1,
+                time: now,
+                previous_frame_duration: Some(now.saturating_duration_since(previous.time)),
+                scheduled
+            }
+        };
+        self.clocks.insert(window_id, clock);
+        clock
+    }
+
+    /// When the main loop must wake up to request the next batch, if there are windows to request
+    pub(crate) fn deadline(&self, now: Instant) -> Option<Instant> {
+        if self.has_unrequested() {
+            Some(self.next_redraw.map_or(now, |next_redraw| next_redraw.max(now)))
+        } else {
+            None
+        }
+    }
+
+    fn has_unrequested(&self) -> bool {
+        self.scheduled.values().any(|scheduled| scheduled.window.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::test_support::window_id;
+    use super::RedrawScheduler;
+
+    #[test]
+    fn requests_due_redraws_at_rate() {
+        let epoch = Instant::now();
+        let at = |millis| epoch + Duration::from_millis(millis);
+        let mut scheduler = RedrawScheduler::<()>::default();
+        assert_eq!(scheduler.deadline(epoch), None);
+        assert_eq!(scheduler.take_due(Some(10), epoch, epoch), []);
+
+        scheduler.schedule(window_id(), (), at(5));
+        scheduler.schedule(window_id(), (), at(6));
+        assert_eq!(scheduler.deadline(at(6)), Some(at(6)));
+        assert_eq!(scheduler.take_due(Some(10), epoch, at(10)), [(window_id(), ())]);
+        // Requested, so nothing to wake up for until it's scheduled again
+        assert_eq!(scheduler.deadline(at(10)), None);
+        assert_eq!(scheduler.take_due(Some(10), epoch, at(20)), []);
+
+        scheduler.schedule(window_id(), (), at(30));
+        assert_eq!(scheduler.deadline(at(30)), Some(at(100)));
+        assert_eq!(scheduler.take_due(Some(10), epoch, at(50)), []);
+        assert_eq!(scheduler.take_due(Some(10), epoch, at(100)), [(window_id(), ())]);
+        // Past the next frame wakes up right away
+        scheduler.schedule(window_id(), (), at(300));
+        assert_eq!(scheduler.deadline(at(300)), Some(at(300)));
+    }
+
+    #[test]
+    fn redraws_advance_frame_clocks() {
+        let epoch = Instant::now();
+        let at = |millis| epoch + Duration::from_millis(millis);
+        let mut scheduler = RedrawScheduler::<()>::default();
+
+        // Requested by the OS
+        let clock = scheduler.redrawn(window_id(), at(10));
+        assert_eq!((clock.frame, clock.previous_frame_duration, clock.scheduled), (0, None, None));
+
+        // Scheduled twice, the first time counts
+        scheduler.schedule(window_id(), (), at(20));
+        scheduler.take_due(None, epoch, at(25));
+        scheduler.schedule(window_id(), (), at(27));
+        let clock = scheduler.redrawn(window_id(), at(30));
+        assert_eq!((clock.frame, clock.previous_frame_duration, clock.scheduled), (1, Some(Duration::from_millis(20)), Some(at(20))));
+
+        // A redraw the OS requested satisfies one which was scheduled but not requested yet
+        scheduler.schedule(window_id(), (), at(40));
+        assert_eq!(scheduler.redrawn(window_id(), at(45)).scheduled, Some(at(40)));
+        assert_eq!(scheduler.deadline(at(45)), None);
+        assert_eq!(scheduler.take_due(None, epoch, at(50)), []);
+
+        scheduler.remove(window_id());
+        assert_eq!(scheduler.redrawn(window_id(), at(60)).frame, 0);
+    }
+}
@@ -1,49 +1,134 @@
-use std::sync::Arc;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::thread::spawn;
+use std::time::{Duration, Instant};
+use winit::event::StartCause;
 use winit::window::WindowBuilder;
 use crossbeam_utils::atomic::AtomicCell;
 use flume::{TryRecvError, TrySendError, unbounded};
-use crate::event_loop::{ControlFlow, SharedControlFlow};
-use crate::event::{Event, UserEvent};
-use crate::messages::{AppProxyRegisterInfo, ProxyRegister, ProxyRegisterBody, ProxyRegisterInfo, ProxyRequest, ProxyResponse, REGISTER_PROXY};
+use crate::event_loop::{ControlFlow, EventIs, EventLoopProxy, EventMeta, next_frame, ProxyId, RequestError, SharedControlFlow};
+use crate::event::{Event, UserEvent, WindowEvent};
+use crate::idle::IdleDetector;
+use crate::redraw::RedrawScheduler;
+use crate::shortcut::ShortcutRegistry;
+use crate::timer::MainTimer;
+use crate::window_state::{WindowState, WindowStates};
+use crate::messages::{AppProxyRegisterInfo, MAIN_PROXY, MainEvent, PendingCall, ProxyRegister, ProxyRegisterBody, ProxyRegisterInfo, ProxyRequest, ProxyResponse, REGISTER_PROXY, Route};
 
 /// Takes control of the main thread and runs the event loop.
 /// The given code will be run on a separate thread.
 /// This code will be able to interact with the event loop via proxy event loops ([event_loop::EventLoop])
 pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
+    let event_loop = winit::event_loop::EventLoop::<MainEvent>::with_user_event();
     let (register_proxy, recv_register) = unbounded();
     // SAFETY: this is the only code which sets, and code which reads should be in threads which didn't spawn yet
     unsafe {
         REGISTER_PROXY = Some(register_proxy);
+        MAIN_PROXY = Some(event_loop.create_proxy());
     }
 
-    // let mut next_proxy_id = 1;
+    let mut next_proxy_id = 1;
     let mut proxy_channels = Vec::new();
+    let mut next_call_id = 0;
+    let mut pending_calls = HashMap::new();
+    let mut timers = Vec::new();
+    let mut redraw_scheduler = RedrawScheduler::default();
+    let mut next_event_seq = 0;
+    let mut iteration = 0;
+    // Every proxy's shortcuts, to detect conflicts
+    let mut shortcut_registry = ShortcutRegistry::default();
+    // Window states for snapshots
+    let mut window_states = WindowStates::new();
+    // Input inactivity, see set_idle_thresholds
+    let mut idle_detector = IdleDetector::new(Instant::now());
+    let mut idle_thresholds_generation = 0;
+    // Lifecycle events to replay to proxies registered later
+    let mut init_seen = false;
+    let mut lifecycle = None;
+    // Frames at the same rate are aligned to this, see ControlFlow::Frame
+    let frame_epoch = Instant::now();
+    // When the previous iteration ended, i.e. when the main loop started waiting
+    let mut wait_start = Instant::now();
 
     EXIT_FLAG.with(|exit_flag| exit_flag.store(1, Ordering::Release));
     spawn(rest);
 
-    winit::event_loop::EventLoop::<UserEvent>::with_user_event().run(move |event, window_target, control_flow| {
+    event_loop.run(move |event, window_target, control_flow| {
+        let now = Instant::now();
+
+        // User events carry who they are for, everything else goes to every proxy.
+        // Wakes don't carry an event, they just make the loop run
+        let (event, route) = match event.map_nonuser_event() {
+            Ok(event) => (Some(event), Route::Broadcast),
+            Err(winit::event::Event::UserEvent(MainEvent::User { route, event })) => (Some(winit::event::Event::UserEvent(event)), route),
+            Err(winit::event::Event::UserEvent(MainEvent::Wake)) => (None, Route::Broadcast),
+            Err(winit::event::Event::UserEvent(MainEvent::AddTimer(timer))) => {
+                timers.push(timer);
+                (None, Route::Broadcast)
+            }
+            Err(_) => unreachable!("map_nonuser_event failed on a non-user event")
+        };
+
         // There is only one non-static event, ScaleFactorChanged, which is very niche. So we just ignore it.
         // We need to be able to clone the events and also send them across thread bounds
         // TODO: rename physical_size to EventOut or something and make it an enum
         // TODO: Also setting physical_size does not actually currently work due to a race condition.
-        let (event, physical_size) = Event::from(event);
+        let (event, physical_size) = match event {
+            Some(event) => {
+                let (event, physical_size) = Event::from(event);
+                (Some(event), physical_size)
+            }
+            None => (None, None)
+        };
+
+        // Every event sent to proxies gets the next sequence number, the same for each proxy
+        if let Some(Event::NewEvents(cause)) = &event {
+            if *cause != StartCause::Init {
+                iteration += 1;
+            }
+        }
+        // Window events carry the window's scale factor, which the state has before ScaleFactorChanged is applied
+        let scale_factor = match &event {
+            Some(Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { scale_factor, .. }, .. }) => Some(*scale_factor),
+            Some(Event::WindowEvent { window_id, .. } | Event::RedrawRequested(window_id)) => {
+                window_states.get(*window_id).and_then(|state| state.scale_factor)
+            }
+            _ => None
+        };
+        let mut next_meta = || {
+            let seq = next_event_seq;
+            next_event_seq += 1;
+            EventMeta { seq, received: now, dispatched: now, iteration, is: EventIs::New, scale_factor: None }
+        };
 
         // Register proxies
-        for ProxyRegister(info) in recv_register.try_iter() {
+        for ProxyRegister { body: info, user_event } in recv_register.try_iter() {
             if let Some(info) = info.upgrade() {
-                // let id = ProxyId(next_proxy_id);
-                // next_proxy_id += 1;
+                let id = ProxyId(next_proxy_id);
+                next_proxy_id += 1;
 
                 let control_flow = Arc::new(AtomicCell::new(ControlFlow::Poll));
+                let timer = Arc::new(AtomicCell::new(None));
                 let (proxy_send, recv_from_proxy) = unbounded();
                 let (send_to_proxy, proxy_recv) = unbounded();
+
+                // Replay the lifecycle events the proxy missed, before it gets live events
+                let missed_init = init_seen.then_some(Event::NewEvents(StartCause::Init));
+                for event in missed_init.into_iter().chain(lifecycle.clone()) {
+                    // The proxy can't disconnect before it gets the other end
+                    let _ = send_to_proxy.try_send(ProxyResponse::Event(event, next_meta()));
+                }
+
                 proxy_channels.push(AppProxyRegisterInfo {
+                    id,
+                    control_flow: control_flow.clone(),
+                    requested_control_flow: ControlFlow::Poll,
+                    timer: timer.clone(),
                     recv_from_proxy,
                     send_to_proxy,
-                    control_flow: control_flow.clone()
+                    topics: HashSet::new(),
+                    user_event
                 });
 
                 match info.take() {
@@ -54,8 +139,9 @@ pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
 
                 info.store(ProxyRegisterBody::Ready {
                     info: ProxyRegisterInfo {
-                        // id,
+                        id,
                         control_flow,
+                        timer,
                         send: proxy_send,
                         recv: proxy_recv,
                     }
@@ -63,11 +149,10 @@ pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
             }
         }
 
-        // Handle proxy messages, send each proxy the event, and get their control_flow policy
-        let mut shared_control_flow = SharedControlFlow::Wait;
+        // Handle proxy messages
         let mut proxy_idxs_to_remove = Vec::new();
-        for (proxy_idx, AppProxyRegisterInfo { control_flow, recv_from_proxy, send_to_proxy}) in proxy_channels.iter_mut().enumerate() {
-            // Handle messages
+        let mut routed_responses = VecDeque::new();
+        for (proxy_idx, AppProxyRegisterInfo { id, recv_from_proxy, send_to_proxy, topics, .. }) in proxy_channels.iter_mut().enumerate() {
             loop {
                 let request = match recv_from_proxy.try_recv() {
                     Ok(request) => request,
@@ -80,11 +165,60 @@ pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
 
                 let response = match request {
                     ProxyRequest::SpawnWindow { configure } => {
-                        ProxyResponse::SpawnWindow { result: configure(WindowBuilder::new()).build(&window_target) }
+                        let result = configure(WindowBuilder::new()).build(window_target);
+                        if let Ok(window) = &result {
+                            window_states.insert(window.id(), WindowState::from_window(window));
+                        }
+                        ProxyResponse::SpawnWindow { result }
+                    }
+                    ProxyRequest::Snapshot => {
+                        ProxyResponse::Snapshot { snapshot: window_states.snapshot() }
+                    }
+                    ProxyRequest::RegisterShortcut { shortcut, scope } => {
+                        ProxyResponse::RegisterShortcut { result: shortcut_registry.register(*id, shortcut, scope) }
+                    }
+                    ProxyRequest::UnregisterShortcut { shortcut, scope } => {
+                        shortcut_registry.unregister(*id, &shortcut, scope);
+                        continue
+                    }
+                    ProxyRequest::SnapshotEvent => {
+                        ProxyResponse::Event(Event::UserEvent(UserEvent::new(window_states.snapshot())), next_meta())
                     }
                     ProxyRequest::RunOnMainThread { action } => {
                         ProxyResponse::RunOnMainThread { return_value: action() }
                     }
+                    ProxyRequest::Call { call_id: caller_call_id, target, request, timeout } => {
+                        let call_id = next_call_id;
+                        next_call_id += 1;
+                        pending_calls.insert(call_id, PendingCall {
+                            caller: *id,
+                            caller_call_id,
+                            target,
+                            deadline: timeout.map(|timeout| Instant::now() + timeout)
+                        });
+                        routed_responses.push_back((target, ProxyResponse::Called { call_id, request }));
+                        continue
+                    }
+                    ProxyRequest::Subscribe { topic } => {
+                        topics.insert(topic);
+                        continue
+                    }
+                    ProxyRequest::Unsubscribe { topic } => {
+                        topics.remove(&topic);
+                        continue
+                    }
+                    ProxyRequest::ScheduleRedraw { window_id, window } => {
+                        redraw_scheduler.schedule(window_id, window, now);
+                        continue
+                    }
+                    ProxyRequest::Return { call_id, response } => {
+                        // If there is no pending call it already timed out
+                        if let Some(PendingCall { caller, caller_call_id, .. }) = pending_calls.remove(&call_id) {
+                            let result = response.ok_or(RequestError::NoHandler);
+                            routed_responses.push_back((caller, ProxyResponse::Call { call_id: caller_call_id, result }));
+                        }
+                        continue
+                    }
                 };
 
                 match send_to_proxy.try_send(response) {
@@ -96,19 +230,72 @@ pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
                     }
                 }
             }
+        }
 
-            // Send the event
-            match send_to_proxy.try_send(ProxyResponse::Event(event.clone())) {
-                Ok(_) => (),
-                Err(TrySendError::Full(_)) => unreachable!("event loop channel (unbounded) full?"),
-                Err(TrySendError::Disconnected(_)) => proxy_idxs_to_remove.push(proxy_idx)
+        // Time out requests between proxies
+        pending_calls.retain(|_, PendingCall { caller, caller_call_id, deadline, .. }| {
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                routed_responses.push_back((*caller, ProxyResponse::Call { call_id: *caller_call_id, result: Err(RequestError::TimedOut) }));
+                false
+            } else {
+                true
             }
+        });
 
-            // Get control flow policy
-            match control_flow.load() {
+        // Forward requests and replies between proxies
+        while let Some((target, response)) = routed_responses.pop_front() {
+            match proxy_channels.iter().find(|proxy| proxy.id == target) {
+                // If sending fails the proxy will be removed next iteration
+                Some(proxy) => drop(proxy.send_to_proxy.try_send(response)),
+                None => if let ProxyResponse::Called { call_id, request: _ } = response {
+                    if let Some(PendingCall { caller, caller_call_id, .. }) = pending_calls.remove(&call_id) {
+                        routed_responses.push_back((caller, ProxyResponse::Call { call_id: caller_call_id, result: Err(RequestError::TargetGone) }));
+                    }
+                }
+            }
+        }
+
+        // Numbered after the replayed and snapshot events, which proxies get first
+        let meta = event.as_ref().map(|_| EventMeta { scale_factor, ..next_meta() });
+        // Every redraw, scheduled or not, advances the window's frame clock
+        let frame_clock = match &event {
+            Some(Event::RedrawRequested(window_id)) => Some((*window_id, redraw_scheduler.redrawn(*window_id, now))),
+            _ => None
+        };
+
+        // Send the event to each proxy it's meant for, and get their control_flow policy
+        let mut shared_control_flow = SharedControlFlow::Wait;
+        for (proxy_idx, proxy) in proxy_channels.iter_mut().enumerate() {
+            if let (Some(event), Some(meta)) = (event.as_ref().filter(|event| route.includes(proxy, event)), meta) {
+                let event = match event {
+                    // Each proxy gets the cause of its own wait
+                    Event::NewEvents(cause) if *cause != StartCause::Init => Event::NewEvents(proxy.start_cause(wait_start, now)),
+                    event => event.clone()
+                };
+                if let Some((window_id, clock)) = frame_clock {
+                    // If sending fails, so does sending the event
+                    let _ = proxy.send_to_proxy.try_send(ProxyResponse::FrameClock { window_id, clock });
+                }
+                let meta = EventMeta { dispatched: Instant::now(), ..meta };
+                match proxy.send_to_proxy.try_send(ProxyResponse::Event(event, meta)) {
+                    Ok(_) => (),
+                    Err(TrySendError::Full(_)) => unreachable!("event loop channel (unbounded) full?"),
+                    Err(TrySendError::Disconnected(_)) => proxy_idxs_to_remove.push(proxy_idx)
+                }
+            }
+
+            if let Some(timer) = proxy.timer.load() {
+                shared_control_flow = shared_control_flow.min(SharedControlFlow::WaitUntil(timer));
+            }
+            proxy.requested_control_flow = match proxy.control_flow.load() {
+                ControlFlow::Frame(rate) => ControlFlow::WaitUntil(next_frame(frame_epoch, rate, now)),
+                control_flow => control_flow
+            };
+            match proxy.requested_control_flow {
                 ControlFlow::Poll => shared_control_flow = shared_control_flow.min(SharedControlFlow::Poll),
                 ControlFlow::Wait => shared_control_flow = shared_control_flow.min(SharedControlFlow::Wait),
                 ControlFlow::WaitUntil(instant) => shared_control_flow = shared_control_flow.min(SharedControlFlow::WaitUntil(instant)),
+                ControlFlow::Frame(_) => unreachable!("just resolved into WaitUntil"),
                 ControlFlow::ExitLocal => {
                     // proxy exits itself, if it actually gets dropped we will remove but it may run again
                 }
@@ -116,16 +303,95 @@ pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
             }
         }
 
+        // Remember lifecycle events and window states after dispatching them,
+        // so proxies registered or snapshotting this iteration don't get them twice
+        match &event {
+            Some(Event::NewEvents(StartCause::Init)) => init_seen = true,
+            Some(event @ (Event::Resumed | Event::Suspended)) => lifecycle = Some(event.clone()),
+            _ => {}
+        }
+        if let Some(event) = &event {
+            window_states.handle_event(event);
+        }
+
+        // Broadcast idle transitions after the input which ended them, or when thresholds pass
+        let generation = IDLE_THRESHOLDS_GENERATION.load(Ordering::Acquire);
+        if generation != idle_thresholds_generation {
+            idle_thresholds_generation = generation;
+            idle_detector.set_thresholds(IDLE_THRESHOLDS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone());
+        }
+        let active = event.as_ref().and_then(|event| idle_detector.handle_event(event, now));
+        for idle_event in active.into_iter().chain(idle_detector.poll(now)) {
+            let idle_event = Event::UserEvent(UserEvent::new(idle_event));
+            let meta = next_meta();
+            for proxy in proxy_channels.iter().filter(|proxy| Route::Broadcast.includes(proxy, &idle_event)) {
+                let meta = EventMeta { dispatched: Instant::now(), ..meta };
+                // If sending fails the proxy will be removed next iteration
+                let _ = proxy.send_to_proxy.try_send(ProxyResponse::Event(idle_event.clone(), meta));
+            }
+        }
+        if let Some(deadline) = idle_detector.deadline() {
+            shared_control_flow = shared_control_flow.min(SharedControlFlow::WaitUntil(deadline));
+        }
+
+        // Request scheduled redraws after the main events, so winit sends them this iteration along with the OS's
+        match &event {
+            Some(Event::MainEventsCleared) => {
+                for (window_id, window) in redraw_scheduler.take_due(REDRAW_RATE.load(), frame_epoch, now) {
+                    match window.upgrade() {
+                        Some(window) => window.request_redraw(),
+                        // Dropped without being destroyed yet
+                        None => redraw_scheduler.remove(window_id)
+                    }
+                }
+            }
+            Some(Event::WindowEvent { window_id, event: WindowEvent::Destroyed }) => redraw_scheduler.remove(*window_id),
+            _ => {}
+        }
+        if let Some(deadline) = redraw_scheduler.deadline(now) {
+            shared_control_flow = shared_control_flow.min(SharedControlFlow::WaitUntil(deadline));
+        }
+
         // Remove disconnected proxies
+        proxy_idxs_to_remove.sort_unstable();
+        proxy_idxs_to_remove.dedup();
         for proxy_to_remove in proxy_idxs_to_remove.into_iter().rev() {
-            proxy_channels.remove(proxy_to_remove);
+            let removed = proxy_channels.remove(proxy_to_remove);
+            shortcut_registry.remove_proxy(removed.id);
+            pending_calls.retain(|_, PendingCall { caller, caller_call_id, target, .. }| {
+                if *target == removed.id {
+                    if let Some(caller) = proxy_channels.iter().find(|proxy| proxy.id == *caller) {
+                        drop(caller.send_to_proxy.try_send(ProxyResponse::Call { call_id: *caller_call_id, result: Err(RequestError::TargetGone) }));
+                    }
+                    false
+                } else {
+                    *caller != removed.id
+                }
+            });
+        }
+
+        // Fire async timers, and wake up to fire the rest
+        timers.retain(|timer: &MainTimer| !timer.fire_if_due(now));
+        if let Some(deadline) = timers.iter().map(|timer| timer.deadline).min() {
+            shared_control_flow = shared_control_flow.min(SharedControlFlow::WaitUntil(deadline));
+        }
+
+        // Wake up to time out requests between proxies
+        if let Some(deadline) = pending_calls.values().filter_map(|call| call.deadline).min() {
+            shared_control_flow = shared_control_flow.min(SharedControlFlow::WaitUntil(deadline));
         }
 
         // Update event and control flow
-        event.into(physical_size);
+        if let Some(event) = event {
+            event.into(physical_size);
+        }
+        wait_start = Instant::now();
         *control_flow = match shared_control_flow {
             SharedControlFlow::Wait => winit::event_loop::ControlFlow::Wait,
-            SharedControlFlow::Poll => winit::event_loop::ControlFlow::Poll,
+            SharedControlFlow::Poll => match POLL_BUDGET.load() {
+                None => winit::event_loop::ControlFlow::Poll,
+                Some(rate) => winit::event_loop::ControlFlow::WaitUntil(next_frame(frame_epoch, rate, Instant::now()))
+            },
             SharedControlFlow::WaitUntil(instant) => winit::event_loop::ControlFlow::WaitUntil(instant),
             SharedControlFlow::ExitApp => winit::event_loop::ControlFlow::Exit,
         };
@@ -136,6 +402,49 @@ pub fn run(rest: impl FnOnce() + Send + 'static) -> ! {
     })
 }
 
+/// Creates an [EventLoopProxy] which can send [UserEvent](crate::event::UserEvent)s to every proxy event loop from any thread.
+///
+/// You must call [run] before this.
+pub fn create_proxy() -> EventLoopProxy {
+    EventLoopProxy::new()
+}
+
+/// Limits how often the main loop runs when a proxy's [ControlFlow] is [ControlFlow::Poll], to `rate`
+/// iterations per second, so polling proxies don't keep the CPU busy.
+/// `None` (the default) removes the limit.
+///
+/// Polling proxies still receive [StartCause::Poll], they just receive it less often.
+pub fn set_poll_budget(rate: Option<u32>) {
+    POLL_BUDGET.store(rate);
+}
+
+/// Limits how often redraws scheduled via [EventLoop::schedule_redraw](crate::event_loop::EventLoop::schedule_redraw)
+/// are requested, to `rate` frames per second, aligned like [ControlFlow::Frame].
+/// `None` (the default) requests them every iteration of the main loop.
+///
+/// Redraws scheduled in between are coalesced, so each window is redrawn at most once per frame.
+pub fn set_redraw_rate(rate: Option<u32>) {
+    REDRAW_RATE.store(rate);
+}
+
+/// Broadcasts an [IdleEvent](crate::idle::IdleEvent) user event to every proxy when there has been no keyboard,
+/// mouse, touch or device input for each of `thresholds`, and another when input resumes.
+/// The main loop wakes up for each threshold, so proxies don't need to poll. No thresholds (the default) disables this.
+///
+/// Thresholds are measured from the last input, so ones shorter than the current inactivity are reported right away.
+pub fn set_idle_thresholds(thresholds: impl IntoIterator<Item = Duration>) {
+    let mut thresholds = thresholds.into_iter().collect::<Vec<_>>();
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    *IDLE_THRESHOLDS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = thresholds;
+    IDLE_THRESHOLDS_GENERATION.fetch_add(1, Ordering::Release);
+    // SAFETY: run sets this before spawning the code which could call this, and afterwards it's only read
+    if let Some(main_proxy) = unsafe { (*std::ptr::addr_of!(MAIN_PROXY)).as_ref() } {
+        // So the main loop wakes up for the new thresholds
+        let _ = main_proxy.send_event(MainEvent::Wake);
+    }
+}
+
 /// Forces the program to exit via winit's event loop.
 ///
 /// If [run] is not called before this it exits normally.
@@ -146,6 +455,12 @@ pub fn exit() {
     EXIT_FLAG.with(|exit_flag| exit_flag.store(2, Ordering::Release));
 }
 
+static POLL_BUDGET: AtomicCell<Option<u32>> = AtomicCell::new(None);
+static REDRAW_RATE: AtomicCell<Option<u32>> = AtomicCell::new(None);
+static IDLE_THRESHOLDS: Mutex<Vec<Duration>> = Mutex::new(Vec::new());
+/// Incremented when [IDLE_THRESHOLDS] changes, so the main loop only locks it then
+static IDLE_THRESHOLDS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
 thread_local! {
     static EXIT_FLAG: Arc<AtomicU8> = Arc::new(AtomicU8::new(0));
 }
\ No newline at end of file
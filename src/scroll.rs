@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use winit::event::{DeviceId, MouseScrollDelta, TouchPhase};
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+use crate::event_loop::EventMeta;
+
+/// Unit of [ScrollEvent] deltas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollUnit {
+    /// Logical pixels, i.e. physical pixels divided by the window's scale factor
+    LogicalPixels,
+    /// Physical pixels
+    PhysicalPixels,
+    /// Lines, where a line is [ScrollConfig::line_height] logical pixels
+    Lines
+}
+
+/// How [ScrollNormalizer] converts deltas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollConfig {
+    pub unit: ScrollUnit,
+    /// Height of a line in logical pixels, used to convert between [MouseScrollDelta::LineDelta] and pixels
+    pub line_height: f64
+}
+
+/// Where a scroll is in its gesture, derived from [TouchPhase].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollPhase {
+    /// Fingers touched the touchpad
+    Started,
+    /// Fingers moved, or a wheel without phases (e.g. a mouse wheel) scrolled
+    Moved,
+    /// Fingers were lifted. Momentum scrolling may follow
+    Ended,
+    /// Inertia after the fingers were lifted. It ends when these stop arriving or another scroll starts
+    Momentum,
+    /// The OS cancelled the scroll
+    Cancelled
+}
+
+/// A normalized [WindowEvent::MouseWheel], returned by [ScrollNormalizer::handle_event].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollEvent {
+    pub window_id: WindowId,
+    pub device_id: DeviceId,
+    pub phase: ScrollPhase,
+    /// Unit of `delta` and `steps`. This is [ScrollConfig::unit], unless converting to it needs the window's scale factor
+    /// and [EventMeta::scale_factor] is `None`: then the delta is left in its own unit,
+    /// [ScrollUnit::Lines] for [MouseScrollDelta::LineDelta] and [ScrollUnit::PhysicalPixels] for [MouseScrollDelta::PixelDelta]
+    pub unit: ScrollUnit,
+    /// The exact delta in `unit`
+    pub delta: (f64, f64),
+    /// Whole units of delta, accumulated across events from the same device so fractions aren't lost.
+    /// Use this for things which scroll in steps, e.g. lists which scroll by row
+    pub steps: (i64, i64)
+}
+
+/// Converts [WindowEvent::MouseWheel] deltas, which are [MouseScrollDelta::LineDelta] or
+/// [MouseScrollDelta::PixelDelta] depending on the device and platform, into one unit,
+/// and derives momentum phases from [TouchPhase]. Pixels are converted with [EventMeta::scale_factor].
+#[derive(Debug, Clone)]
+pub struct ScrollNormalizer {
+    config: ScrollConfig,
+    devices: HashMap<(WindowId, DeviceId), DeviceScroll>
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DeviceScroll {
+    /// Fingers were lifted, so further moves are momentum
+    lifted: bool,
+    /// Fractional units not yet reported in [ScrollEvent::steps]
+    remainder: (f64, f64)
+}
+
+impl Default for ScrollConfig {
+    fn default() -> Self {
+        ScrollConfig {
+            unit: ScrollUnit::LogicalPixels,
+            line_height: 20.0
+        }
+    }
+}
+
+impl ScrollNormalizer {
+    pub fn new(config: ScrollConfig) -> Self {
+        ScrollNormalizer {
+            config,
+            devices: HashMap::new()
+        }
+    }
+
+    pub fn config(&self) -> &ScrollConfig {
+        &self.config
+    }
+
+    /// Normalizes a [WindowEvent::MouseWheel] with the `meta` it was received with, and forgets destroyed windows
+    pub fn handle_event<T>(&mut self, event: &Event<T>, meta: &EventMeta) -> Option<ScrollEvent> {
+        let (window_id, event) = match event {
+            Event::WindowEvent { window_id, event } => (*window_id, event),
+            _ => return None
+        };
+        match event {
+            WindowEvent::Destroyed => {
+                self.devices.retain(|(device_window_id, _), _| *device_window_id != window_id);
+                None
+            }
+            WindowEvent::MouseWheel { device_id, delta, phase, .. } => {
+                let (unit, delta) = self.convert(delta, meta.scale_factor);
+                let device = self.devices.entry((window_id, *device_id)).or_default();
+                let phase = match phase {
+                    TouchPhase::Started => {
+                        // A new scroll interrupts momentum, so don't carry its fractions
+                        *device = DeviceScroll::default();
+                        ScrollPhase::Started
+                    }
+                    TouchPhase::Moved if device.lifted => ScrollPhase::Momentum,
+                    TouchPhase::Moved => ScrollPhase::Moved,
+                    TouchPhase::Ended => {
+                        device.lifted = true;
+                        ScrollPhase::Ended
+                    }
+                    TouchPhase::Cancelled => {
+                        device.lifted = false;
+                        ScrollPhase::Cancelled
+                    }
+                };
+                let x = device.remainder.0 + delta.0;
+                let y = device.remainder.1 + delta.1;
+                let steps = (x.trunc(), y.trunc());
+                device.remainder = (x - steps.0, y - steps.1);
+                Some(ScrollEvent {
+                    window_id,
+                    device_id: *device_id,
+                    phase,
+                    unit,
+                    delta,
+                    steps: (steps.0 as i64, steps.1 as i64)
+                })
+            }
+            _ => None
+        }
+    }
+
+    /// Converts the delta into the configured unit, or leaves it in its own unit if that needs the unknown scale factor
+    fn convert(&self, delta: &MouseScrollDelta, scale_factor: Option<f64>) -> (ScrollUnit, (f64, f64)) {
+        let line_height = self.config.line_height;
+        let unit = self.config.unit;
+        match (*delta, unit, scale_factor) {
+            (MouseScrollDelta::LineDelta(x, y), ScrollUnit::Lines, _) => (unit, (x as f64, y as f64)),
+            (MouseScrollDelta::LineDelta(x, y), ScrollUnit::LogicalPixels, _) => (unit, (x as f64 * line_height, y as f64 * line_height)),
+            (MouseScrollDelta::LineDelta(x, y), ScrollUnit::PhysicalPixels, Some(scale_factor)) => {
+                let pixels = line_height * scale_factor;
+                (unit, (x as f64 * pixels, y as f64 * pixels))
+            }
+            (MouseScrollDelta::LineDelta(x, y), ScrollUnit::PhysicalPixels, None) => (ScrollUnit::Lines, (x as f64, y as f64)),
+            (MouseScrollDelta::PixelDelta(position), ScrollUnit::PhysicalPixels, _) => (unit, (position.x, position.y)),
+            (MouseScrollDelta::PixelDelta(position), ScrollUnit::LogicalPixels, Some(scale_factor)) => {
+                (unit, (position.x / scale_factor, position.y / scale_factor))
+            }
+            (MouseScrollDelta::PixelDelta(position), ScrollUnit::Lines, Some(scale_factor)) => {
+                let pixels = line_height * scale_factor;
+                (unit, (position.x / pixels, position.y / pixels))
+            }
+            (MouseScrollDelta::PixelDelta(position), ScrollUnit::LogicalPixels | ScrollUnit::Lines, None) => {
+                (ScrollUnit::PhysicalPixels, (position.x, position.y))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{ModifiersState, MouseScrollDelta, TouchPhase};
+    use crate::event::{Event, WindowEvent};
+    use crate::event_loop::EventMeta;
+    use crate::test_support::{device_id, meta, window_id};
+    use super::{ScrollConfig, ScrollNormalizer, ScrollPhase, ScrollUnit};
+
+    fn wheel(delta: MouseScrollDelta, phase: TouchPhase) -> Event {
+        Event::WindowEvent { window_id: window_id(), event: WindowEvent::MouseWheel { device_id: device_id(), delta, phase, modifiers: ModifiersState::empty() } }
+    }
+
+    fn pixels(y: f64, phase: TouchPhase) -> Event {
+        wheel(MouseScrollDelta::PixelDelta(PhysicalPosition::new(0.0, y)), phase)
+    }
+
+    fn normalizer(unit: ScrollUnit) -> ScrollNormalizer {
+        ScrollNormalizer::new(ScrollConfig { unit, ..ScrollConfig::default() })
+    }
+
+    fn scaled() -> EventMeta {
+        meta(Some(2.0))
+    }
+
+    #[test]
+    fn converts_units() {
+        let line = wheel(MouseScrollDelta::LineDelta(1.0, -2.0), TouchPhase::Moved);
+        let pixel = pixels(80.0, TouchPhase::Moved);
+        let delta = |unit, event| normalizer(unit).handle_event(event, &scaled()).unwrap().delta;
+        assert_eq!(delta(ScrollUnit::Lines, &line), (1.0, -2.0));
+        assert_eq!(delta(ScrollUnit::LogicalPixels, &line), (20.0, -40.0));
+        assert_eq!(delta(ScrollUnit::PhysicalPixels, &line), (40.0, -80.0));
+        assert_eq!(delta(ScrollUnit::PhysicalPixels, &pixel), (0.0, 80.0));
+        assert_eq!(delta(ScrollUnit::LogicalPixels, &pixel), (0.0, 40.0));
+        assert_eq!(delta(ScrollUnit::Lines, &pixel), (0.0, 2.0));
+    }
+
+    #[test]
+    fn keeps_own_unit_without_scale_factor() {
+        let line = wheel(MouseScrollDelta::LineDelta(1.0, -2.0), TouchPhase::Moved);
+        let pixel = pixels(80.0, TouchPhase::Moved);
+        let converted = |unit, event| {
+            let scroll = normalizer(unit).handle_event(event, &meta(None)).unwrap();
+            (scroll.unit, scroll.delta)
+        };
+        // Doesn't need the scale factor
+        assert_eq!(converted(ScrollUnit::LogicalPixels, &line), (ScrollUnit::LogicalPixels, (20.0, -40.0)));
+        assert_eq!(converted(ScrollUnit::PhysicalPixels, &pixel), (ScrollUnit::PhysicalPixels, (0.0, 80.0)));
+        // Needs it
+        assert_eq!(converted(ScrollUnit::PhysicalPixels, &line), (ScrollUnit::Lines, (1.0, -2.0)));
+        assert_eq!(converted(ScrollUnit::LogicalPixels, &pixel), (ScrollUnit::PhysicalPixels, (0.0, 80.0)));
+        assert_eq!(converted(ScrollUnit::Lines, &pixel), (ScrollUnit::PhysicalPixels, (0.0, 80.0)));
+    }
+
+    #[test]
+    fn forgets_destroyed_windows() {
+        let mut normalizer = normalizer(ScrollUnit::Lines);
+        assert_eq!(normalizer.handle_event(&pixels(-32.0, TouchPhase::Moved), &scaled()).unwrap().steps, (0, 0));
+        let destroyed: Event = Event::WindowEvent { window_id: window_id(), event: WindowEvent::Destroyed };
+        assert_eq!(normalizer.handle_event(&destroyed, &scaled()), None);
+        // The leftover -0.8 lines is gone
+        assert_eq!(normalizer.handle_event(&pixels(-32.0, TouchPhase::Moved), &scaled()).unwrap().steps, (0, 0));
+    }
+
+    #[test]
+    fn momentum_follows_end() {
+        let mut normalizer = normalizer(ScrollUnit::PhysicalPixels);
+        let phases = [TouchPhase::Started, TouchPhase::Moved, TouchPhase::Ended, TouchPhase::Moved, TouchPhase::Moved, TouchPhase::Started, TouchPhase::Moved]
+            .into_iter()
+            .map(|phase| normalizer.handle_event(&pixels(1.0, phase), &scaled()).unwrap().phase)
+            .collect::<Vec<_>>();
+        assert_eq!(phases, [
+            ScrollPhase::Started, ScrollPhase::Moved, ScrollPhase::Ended,
+            ScrollPhase::Momentum, ScrollPhase::Momentum, ScrollPhase::Started, ScrollPhase::Moved
+        ]);
+    }
+
+    #[test]
+    fn accumulates_fractional_steps() {
+        let mut normalizer = normalizer(ScrollUnit::Lines);
+        let steps = (0..4)
+            .map(|_| normalizer.handle_event(&pixels(-16.0, TouchPhase::Moved), &scaled()).unwrap().steps.1)
+            .collect::<Vec<_>>();
+        // Each event is -0.4 lines
+        assert_eq!(steps, [0, 0, -1, 0]);
+        // Starting a new scroll drops the leftover -0.6
+        assert_eq!(normalizer.handle_event(&pixels(-16.0, TouchPhase::Started), &scaled()).unwrap().steps, (0, 0));
+        assert_eq!(normalizer.handle_event(&pixels(-32.0, TouchPhase::Moved), &scaled()).unwrap().steps, (0, -1));
+    }
+}
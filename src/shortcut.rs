@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use winit::event::{ElementState, ModifiersState, VirtualKeyCode};
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+use crate::event_loop::ProxyId;
+
+/// A key with modifiers, e.g. `Ctrl+Shift+P`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyStroke {
+    pub modifiers: ModifiersState,
+    pub key: VirtualKeyCode
+}
+
+/// A sequence of [KeyStroke]s pressed one after another, e.g. `Ctrl+K Ctrl+S`.
+/// Most shortcuts are just one stroke.
+///
+/// Parse from a string: strokes are separated by spaces, and keys and modifiers in a stroke by `+`.
+/// Modifiers are `Ctrl`, `Shift`, `Alt` and `Super` (or `Cmd`), and case doesn't matter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    strokes: Vec<KeyStroke>
+}
+
+/// Where a shortcut is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShortcutScope {
+    /// In every window
+    Global,
+    /// Only when the key events are for this window
+    Window(WindowId)
+}
+
+/// User event sent to the proxy which registered a shortcut (see [EventLoop::register_shortcut](crate::event_loop::EventLoop::register_shortcut))
+/// after the key event which completed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutTriggered {
+    /// The name it was registered with
+    pub name: String,
+    pub shortcut: Shortcut,
+    /// The window the key events were for
+    pub window_id: WindowId
+}
+
+/// A shortcut string couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShortcutParseError {
+    Empty,
+    /// A stroke has modifiers but no key, e.g. `Ctrl+`
+    MissingKey(String),
+    /// A stroke has multiple non-modifier keys, e.g. `A+B`
+    MultipleKeys(String),
+    UnknownKey(String)
+}
+
+/// A shortcut couldn't be registered because it conflicts with one registered by this or another proxy:
+/// their scopes overlap and one starts with the other, so it would be ambiguous.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutConflict {
+    /// The already registered shortcut
+    pub shortcut: Shortcut,
+    pub scope: ShortcutScope,
+    /// The proxy which registered it
+    pub proxy: ProxyId
+}
+
+/// Proxy state: the shortcuts this proxy registered and the strokes typed so far in each window
+#[derive(Debug)]
+pub(crate) struct ShortcutMatcher {
+    bindings: Vec<(String, Shortcut, ShortcutScope)>,
+    chord_timeout: Duration,
+    windows: HashMap<WindowId, WindowChord>
+}
+
+#[derive(Debug, Default)]
+struct WindowChord {
+    modifiers: ModifiersState,
+    strokes: Vec<KeyStroke>,
+    last_stroke: Option<Instant>
+}
+
+/// Main loop state: the shortcuts every proxy registered
+#[derive(Debug, Default)]
+pub(crate) struct ShortcutRegistry {
+    registrations: Vec<ShortcutConflict>
+}
+
+impl Shortcut {
+    pub fn new(strokes: Vec<KeyStroke>) -> Self {
+        Shortcut { strokes }
+    }
+
+    pub fn strokes(&self) -> &[KeyStroke] {
+        &self.strokes
+    }
+
+    /// Whether pressing this shortcut would first press `other`, or they are the same
+    pub fn starts_with(&self, other: &Shortcut) -> bool {
+        self.strokes.starts_with(&other.strokes)
+    }
+}
+
+impl From<KeyStroke> for Shortcut {
+    fn from(stroke: KeyStroke) -> Self {
+        Shortcut { strokes: vec![stroke] }
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = ShortcutParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let strokes = s.split_whitespace().map(KeyStroke::from_str).collect::<Result<Vec<_>, _>>()?;
+        if strokes.is_empty() {
+            return Err(ShortcutParseError::Empty);
+        }
+        Ok(Shortcut { strokes })
+    }
+}
+
+impl FromStr for KeyStroke {
+    type Err = ShortcutParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = ModifiersState::empty();
+        let mut key = None;
+        for part in s.split('+') {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= ModifiersState::CTRL,
+                "shift" => modifiers |= ModifiersState::SHIFT,
+                "alt" | "option" => modifiers |= ModifiersState::ALT,
+                "super" | "cmd" | "command" | "meta" | "win" | "logo" => modifiers |= ModifiersState::LOGO,
+                "" => return Err(ShortcutParseError::MissingKey(s.to_string())),
+                _ => {
+                    let part_key = parse_key(part).ok_or_else(|| ShortcutParseError::UnknownKey(part.to_string()))?;
+                    if key.replace(part_key).is_some() {
+                        return Err(ShortcutParseError::MultipleKeys(s.to_string()));
+                    }
+                }
+            }
+        }
+        match key {
+            None => Err(ShortcutParseError::MissingKey(s.to_string())),
+            Some(key) => Ok(KeyStroke { modifiers, key })
+        }
+    }
+}
+
+impl Display for KeyStroke {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.ctrl() {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift() {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt() {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.logo() {
+            write!(f, "Super+")?;
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+impl Display for Shortcut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, stroke) in self.strokes.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", stroke)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ShortcutParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShortcutParseError::Empty => write!(f, "shortcut is empty"),
+            ShortcutParseError::MissingKey(stroke) => write!(f, "no key in {:?}", stroke),
+            ShortcutParseError::MultipleKeys(stroke) => write!(f, "multiple keys in {:?}, use spaces to separate strokes", stroke),
+            ShortcutParseError::UnknownKey(key) => write!(f, "unknown key {:?}", key)
+        }
+    }
+}
+
+impl Error for ShortcutParseError {}
+
+impl Display for ShortcutConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "conflicts with {} ({:?}) registered by {:?}", self.shortcut, self.scope, self.proxy)
+    }
+}
+
+impl Error for ShortcutConflict {}
+
+impl ShortcutScope {
+    fn overlaps(&self, other: &ShortcutScope) -> bool {
+        match (self, other) {
+            (ShortcutScope::Window(a), ShortcutScope::Window(b)) => a == b,
+            _ => true
+        }
+    }
+
+    fn includes(&self, window_id: WindowId) -> bool {
+        match self {
+            ShortcutScope::Global => true,
+            ShortcutScope::Window(scope_window_id) => *scope_window_id == window_id
+        }
+    }
+}
+
+impl ShortcutMatcher {
+    pub(crate) fn new() -> Self {
+        ShortcutMatcher {
+            bindings: Vec::new(),
+            chord_timeout: Duration::from_secs(1),
+            windows: HashMap::new()
+        }
+    }
+
+    pub(crate) fn bind(&mut self, name: String, shortcut: Shortcut, scope: ShortcutScope) {
+        self.bindings.push((name, shortcut, scope));
+    }
+
+    /// Removes the binding with the name, returning it
+    pub(crate) fn unbind(&mut self, name: &str) -> Option<(Shortcut, ShortcutScope)> {
+        let idx = self.bindings.iter().position(|(binding_name, _, _)| binding_name == name)?;
+        let (_, shortcut, scope) = self.bindings.remove(idx);
+        Some((shortcut, scope))
+    }
+
+    pub(crate) fn set_chord_timeout(&mut self, chord_timeout: Duration) {
+        self.chord_timeout = chord_timeout;
+    }
+
+    /// Tracks modifiers and strokes, returning the shortcuts the event completed
+    pub(crate) fn handle_event<T>(&mut self, event: &Event<T>, now: Instant) -> Vec<ShortcutTriggered> {
+        let (window_id, event) = match event {
+            Event::WindowEvent { window_id, event } => (*window_id, event),
+            _ => return Vec::new()
+        };
+        let key = match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.windows.entry(window_id).or_default().modifiers = *modifiers;
+                return Vec::new()
+            }
+            WindowEvent::Destroyed => {
+                self.windows.remove(&window_id);
+                return Vec::new()
+            }
+            WindowEvent::KeyboardInput { input, .. } if input.state == ElementState::Pressed => match input.virtual_keycode {
+                Some(key) if !is_modifier(key) => key,
+                _ => return Vec::new()
+            },
+            _ => return Vec::new()
+        };
+
+        let chord_timeout = self.chord_timeout;
+        let window = self.windows.entry(window_id).or_default();
+        if window.last_stroke.is_some_and(|last_stroke| now.saturating_duration_since(last_stroke) > chord_timeout) {
+            window.strokes.clear();
+        }
+        window.last_stroke = Some(now);
+        let stroke = KeyStroke { modifiers: window.modifiers, key };
+        window.strokes.push(stroke);
+
+        // If the strokes so far don't lead anywhere, the new stroke may start another shortcut
+        let mut strokes = Shortcut { strokes: std::mem::take(&mut window.strokes) };
+        if !self.is_prefix(&strokes, window_id) {
+            strokes = Shortcut::from(stroke);
+        }
+        let triggered = self.bindings.iter()
+            .filter(|(_, shortcut, scope)| scope.includes(window_id) && *shortcut == strokes)
+            .map(|(name, shortcut, _)| ShortcutTriggered { name: name.clone(), shortcut: shortcut.clone(), window_id })
+            .collect::<Vec<_>>();
+        // Otherwise wait for the rest of the chord
+        if triggered.is_empty() && self.is_prefix(&strokes, window_id) {
+            self.windows.entry(window_id).or_default().strokes = strokes.strokes;
+        }
+        triggered
+    }
+
+    fn is_prefix(&self, strokes: &Shortcut, window_id: WindowId) -> bool {
+        self.bindings.iter().any(|(_, shortcut, scope)| scope.includes(window_id) && shortcut.starts_with(strokes))
+    }
+}
+
+impl ShortcutRegistry {
+    /// Registers the shortcut unless it conflicts
+    pub(crate) fn register(&mut self, proxy: ProxyId, shortcut: Shortcut, scope: ShortcutScope) -> Result<(), ShortcutConflict> {
+        if let Some(conflict) = self.registrations.iter().find(|registration| {
+            registration.scope.overlaps(&scope) && (registration.shortcut.starts_with(&shortcut) || shortcut.starts_with(&registration.shortcut))
+        }) {
+            return Err(conflict.clone());
+        }
+        self.registrations.push(ShortcutConflict { shortcut, scope, proxy });
+        Ok(())
+    }
+
+    pub(crate) fn unregister(&mut self, proxy: ProxyId, shortcut: &Shortcut, scope: ShortcutScope) {
+        self.registrations.retain(|registration| !(registration.proxy == proxy && registration.shortcut == *shortcut && registration.scope == scope));
+    }
+
+    /// Unregisters everything a removed proxy registered
+    pub(crate) fn remove_proxy(&mut self, proxy: ProxyId) {
+        self.registrations.retain(|registration| registration.proxy != proxy);
+    }
+}
+
+fn is_modifier(key: VirtualKeyCode) -> bool {
+    matches!(key,
+        VirtualKeyCode::LControl | VirtualKeyCode::RControl |
+        VirtualKeyCode::LShift | VirtualKeyCode::RShift |
+        VirtualKeyCode::LAlt | VirtualKeyCode::RAlt |
+        VirtualKeyCode::LWin | VirtualKeyCode::RWin)
+}
+
+/// The name of a key which [parse_key] parses back
+pub(crate) fn key_name(key: VirtualKeyCode) -> String {
+    match key {
+        VirtualKeyCode::Key0 => "0".to_string(),
+        VirtualKeyCode::Key1 => "1".to_string(),
+        VirtualKeyCode::Key2 => "2".to_string(),
+        VirtualKeyCode::Key3 => "3".to_string(),
+        VirtualKeyCode::Key4 => "4".to_string(),
+        VirtualKeyCode::Key5 => "5".to_string(),
+        VirtualKeyCode::Key6 => "6".to_string(),
+        VirtualKeyCode::Key7 => "7".to_string(),
+        VirtualKeyCode::Key8 => "8".to_string(),
+        VirtualKeyCode::Key9 => "9".to_string(),
+        key => format!("{:?}", key)
+    }
+}
+
+/// Parses a key name: the [VirtualKeyCode] variant name (case-insensitive), a single letter or digit, or a common alias
+pub(crate) fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode::*;
+    let name = name.to_ascii_lowercase();
+    Some(match name.as_str() {
+        "0" => Key0, "1" => Key1, "2" => Key2, "3" => Key3, "4" => Key4,
+        "5" => Key5, "6" => Key6, "7" => Key7, "8" => Key8, "9" => Key9,
+        "a" => A, "b" => B, "c" => C, "d" => D, "e" => E, "f" => F, "g" => G,
+        "h" => H, "i" => I, "j" => J, "k" => K, "l" => L, "m" => M, "n" => N,
+        "o" => O, "p" => P, "q" => Q, "r" => R, "s" => S, "t" => T, "u" => U,
+        "v" => V, "w" => W, "x" => X, "y" => Y, "z" => Z,
+        "f1" => F1, "f2" => F2, "f3" => F3, "f4" => F4, "f5" => F5, "f6" => F6,
+        "f7" => F7, "f8" => F8, "f9" => F9, "f10" => F10, "f11" => F11, "f12" => F12,
+        "f13" => F13, "f14" => F14, "f15" => F15, "f16" => F16, "f17" => F17, "f18" => F18,
+        "f19" => F19, "f20" => F20, "f21" => F21, "f22" => F22, "f23" => F23, "f24" => F24,
+        "escape" | "esc" => Escape,
+        "return" | "enter" => Return,
+        "space" => Space,
+        "tab" => Tab,
+        "back" | "backspace" => Back,
+        "delete" | "del" => Delete,
+        "insert" | "ins" => Insert,
+        "home" => Home,
+        "end" => End,
+        "pageup" => PageUp,
+        "pagedown" => PageDown,
+        "up" => Up,
+        "down" => Down,
+        "left" => Left,
+        "right" => Right,
+        "minus" | "-" => Minus,
+        "equals" | "=" => Equals,
+        "plus" => Plus,
+        "comma" | "," => Comma,
+        "period" | "." => Period,
+        "slash" | "/" => Slash,
+        "backslash" | "\\" => Backslash,
+        "semicolon" | ";" => Semicolon,
+        "apostrophe" | "'" => Apostrophe,
+        "grave" | "`" => Grave,
+        "lbracket" | "[" => LBracket,
+        "rbracket" | "]" => RBracket,
+        "lshift" => LShift,
+        "rshift" => RShift,
+        "lcontrol" | "lctrl" => LControl,
+        "rcontrol" | "rctrl" => RControl,
+        "lalt" => LAlt,
+        "ralt" => RAlt,
+        "lwin" => LWin,
+        "rwin" => RWin,
+        name => return KEY_CODES.iter().find(|(key_name, _)| key_name.eq_ignore_ascii_case(name)).map(|(_, key)| *key)
+    })
+}
+
+macro_rules! key_codes {
+    ($($key:ident)*) => {
+        /// Every [VirtualKeyCode] with its variant name
+        pub(crate) const KEY_CODES: &[(&str, VirtualKeyCode)] = &[$((stringify!($key), VirtualKeyCode::$key)),*];
+
+        /// Doesn't compile if a [VirtualKeyCode] is missing from [KEY_CODES]
+        #[allow(dead_code)]
+        fn every_key_code_is_listed(key: VirtualKeyCode) {
+            match key {
+                $(VirtualKeyCode::$key => ()),*
+            }
+        }
+    };
+}
+
+key_codes!(
+    Key1 Key2 Key3 Key4 Key5 Key6 Key7 Key8 Key9 Key0 A B C D E F G H I J K L M N O P Q R S T U V W X Y Z
+    Escape F1 F2 F3 F4 F5 F6 F7 F8 F9 F10 F11 F12 F13 F14 F15 F16 F17 F18 F19 F20 F21 F22 F23 F24 Snapshot
+    Scroll Pause Insert Home Delete End PageDown PageUp Left Up Right Down Back Return Space Compose Caret
+    Numlock Numpad0 Numpad1 Numpad2 Numpad3 Numpad4 Numpad5 Numpad6 Numpad7 Numpad8 Numpad9 NumpadAdd
+    NumpadDivide NumpadDecimal NumpadComma NumpadEnter NumpadEquals NumpadMultiply NumpadSubtract AbntC1
+    AbntC2 Apostrophe Apps Asterisk At Ax Backslash Calculator Capital Colon Comma Convert Equals Grave Kana
+    Kanji LAlt LBracket LControl LShift LWin Mail MediaSelect MediaStop Minus Mute MyComputer NavigateForward
+    NavigateBackward NextTrack NoConvert OEM102 Period PlayPause Plus Power PrevTrack RAlt RBracket RControl
+    RShift RWin Semicolon Slash Sleep Stop Sysrq Tab Underline Unlabeled VolumeDown VolumeUp Wake WebBack
+    WebFavorites WebForward WebHome WebRefresh WebSearch WebStop Yen Copy Paste Cut
+);
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use winit::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode};
+    use crate::event::{Event, WindowEvent};
+    use crate::event_loop::ProxyId;
+    use crate::test_support::{device_id, window_id};
+    use super::{KEY_CODES, KeyStroke, Shortcut, ShortcutMatcher, ShortcutParseError, ShortcutRegistry, ShortcutScope};
+
+    fn event(event: WindowEvent) -> Event {
+        Event::WindowEvent { window_id: window_id(), event }
+    }
+
+    fn press(key: VirtualKeyCode) -> Event {
+        let input = KeyboardInput { scancode: 0, state: ElementState::Pressed, virtual_keycode: Some(key), modifiers: ModifiersState::empty() };
+        event(WindowEvent::KeyboardInput { device_id: device_id(), input, is_synthetic: false })
+    }
+
+    fn shortcut(s: &str) -> Shortcut {
+        s.parse().unwrap()
+    }
+
+    fn triggered(matcher: &mut ShortcutMatcher, event: Event, now: Instant) -> Vec<String> {
+        matcher.handle_event(&event, now).into_iter().map(|triggered| triggered.name).collect()
+    }
+
+    #[test]
+    fn parses_strokes() {
+        assert_eq!(shortcut("Ctrl+Shift+P"), Shortcut::from(KeyStroke { modifiers: ModifiersState::CTRL | ModifiersState::SHIFT, key: VirtualKeyCode::P }));
+        assert_eq!(shortcut("cmd+numpad1").strokes(), [KeyStroke { modifiers: ModifiersState::LOGO, key: VirtualKeyCode::Numpad1 }]);
+        assert_eq!(shortcut("Key1"), shortcut("1"));
+        assert_eq!(shortcut("ctrl+k  ctrl+s").strokes().len(), 2);
+        assert_eq!("".parse::<Shortcut>(), Err(ShortcutParseError::Empty));
+        assert_eq!("Ctrl+".parse::<Shortcut>(), Err(ShortcutParseError::MissingKey("Ctrl+".to_string())));
+        assert_eq!("A+B".parse::<Shortcut>(), Err(ShortcutParseError::MultipleKeys("A+B".to_string())));
+        assert_eq!("Ctrl+Nope".parse::<Shortcut>(), Err(ShortcutParseError::UnknownKey("Nope".to_string())));
+    }
+
+    #[test]
+    fn display_round_trips_every_key() {
+        for (_, key) in KEY_CODES {
+            let stroke = Shortcut::from(KeyStroke { modifiers: ModifiersState::all(), key: *key });
+            assert_eq!(shortcut(&stroke.to_string()), stroke);
+        }
+        assert_eq!(shortcut("shift+ctrl+k ctrl+s").to_string(), "Ctrl+Shift+K Ctrl+S");
+    }
+
+    #[test]
+    fn matches_chords() {
+        let mut matcher = ShortcutMatcher::new();
+        matcher.bind("save all".to_string(), shortcut("Ctrl+K S"), ShortcutScope::Global);
+        matcher.bind("save".to_string(), shortcut("Ctrl+S"), ShortcutScope::Global);
+        let now = Instant::now();
+        assert_eq!(triggered(&mut matcher, event(WindowEvent::ModifiersChanged(ModifiersState::CTRL)), now), [] as [String; 0]);
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::K), now), [] as [String; 0]);
+        // Modifier keys don't interrupt the chord
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::LControl), now), [] as [String; 0]);
+        triggered(&mut matcher, event(WindowEvent::ModifiersChanged(ModifiersState::empty())), now);
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::S), now), ["save all"]);
+        // A stroke which doesn't continue the chord can start another shortcut
+        triggered(&mut matcher, event(WindowEvent::ModifiersChanged(ModifiersState::CTRL)), now);
+        triggered(&mut matcher, press(VirtualKeyCode::K), now);
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::S), now), ["save"]);
+    }
+
+    #[test]
+    fn chords_time_out() {
+        let mut matcher = ShortcutMatcher::new();
+        matcher.set_chord_timeout(Duration::from_millis(500));
+        matcher.bind("chord".to_string(), shortcut("G G"), ShortcutScope::Window(window_id()));
+        let start = Instant::now();
+        triggered(&mut matcher, press(VirtualKeyCode::G), start);
+        let late = start + Duration::from_millis(600);
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::G), late), [] as [String; 0]);
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::G), late + Duration::from_millis(100)), ["chord"]);
+        assert!(matcher.unbind("chord").is_some());
+        triggered(&mut matcher, press(VirtualKeyCode::G), late);
+        assert_eq!(triggered(&mut matcher, press(VirtualKeyCode::G), late), [] as [String; 0]);
+    }
+
+    #[test]
+    fn registry_rejects_conflicts() {
+        let mut registry = ShortcutRegistry::default();
+        let (a, b) = (ProxyId(1), ProxyId(2));
+        let window = ShortcutScope::Window(window_id());
+        registry.register(a, shortcut("Ctrl+K Ctrl+S"), window).unwrap();
+        // Prefixes and extensions in overlapping scopes are ambiguous
+        let conflict = registry.register(b, shortcut("Ctrl+K"), ShortcutScope::Global).unwrap_err();
+        assert_eq!((conflict.shortcut, conflict.scope, conflict.proxy), (shortcut("Ctrl+K Ctrl+S"), window, a));
+        assert!(registry.register(b, shortcut("Ctrl+K Ctrl+S Ctrl+S"), window).is_err());
+        assert!(registry.register(b, shortcut("Ctrl+K Ctrl+O"), window).is_ok());
+        registry.unregister(a, &shortcut("Ctrl+K Ctrl+S"), window);
+        assert!(registry.register(b, shortcut("Ctrl+K Ctrl+S"), window).is_ok());
+        registry.remove_proxy(b);
+        assert!(registry.register(a, shortcut("Ctrl+K"), ShortcutScope::Global).is_ok());
+    }
+}
@@ -0,0 +1,20 @@
+use std::time::Instant;
+use winit::event::DeviceId;
+use winit::window::WindowId;
+use crate::event_loop::{EventIs, EventMeta};
+
+// SAFETY: for both, dummy ids are only compared, never passed to winit
+
+pub(crate) fn window_id() -> WindowId {
+    unsafe { WindowId::dummy() }
+}
+
+pub(crate) fn device_id() -> DeviceId {
+    unsafe { DeviceId::dummy() }
+}
+
+/// Meta of a new event received now
+pub(crate) fn meta(scale_factor: Option<f64>) -> EventMeta {
+    let now = Instant::now();
+    EventMeta { seq: 0, received: now, dispatched: now, iteration: 0, is: EventIs::New, scale_factor }
+}
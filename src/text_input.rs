@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use winit::event::{ElementState, ModifiersState, VirtualKeyCode};
+use winit::window::WindowId;
+use crate::event::{Event, WindowEvent};
+
+/// Which way a [TextCommand] goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextDirection {
+    /// Left or up
+    Backward,
+    /// Right or down
+    Forward
+}
+
+/// How far a [TextCommand] moves the caret or deletes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextMovement {
+    /// To the previous or next character (arrow keys)
+    Char(TextDirection),
+    /// To the previous word start or next word end (Ctrl+arrow keys, or Option on macOS)
+    Word(TextDirection),
+    /// To the start or end of the line (Home/End, or Cmd+arrow keys on macOS)
+    LineBoundary(TextDirection),
+    /// To the line above or below (up/down arrow keys)
+    Line(TextDirection),
+    /// To the start or end of the text (Ctrl+Home/End, or Cmd+up/down on macOS)
+    Document(TextDirection)
+}
+
+/// Editing command for a text widget, see [TextInput].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TextCommand {
+    /// Insert text at the caret, replacing the selection if any
+    Insert(String),
+    /// Enter was pressed. Multiline widgets insert a newline, others may submit
+    Newline,
+    /// Tab was pressed, [TextDirection::Backward] with Shift. Widgets may insert a tab or move focus.
+    /// Tab with Ctrl, Alt or Cmd held is a shortcut (e.g. switching tabs) and produces no command
+    Tab(TextDirection),
+    /// Delete the selection if any, otherwise from the caret to where `movement` would move it
+    Delete { movement: TextMovement },
+    /// Move the caret. If `select` is set (Shift is held), extend the selection, otherwise clear it
+    MoveCaret { movement: TextMovement, select: bool },
+    /// Select all text (Ctrl+A, or Cmd+A on macOS)
+    SelectAll
+}
+
+/// A [TextCommand] for a window, returned by [TextInput::handle_event].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextInputEvent {
+    pub window_id: WindowId,
+    pub command: TextCommand
+}
+
+/// Turns [WindowEvent::ReceivedCharacter] and [WindowEvent::KeyboardInput] into [TextCommand]s,
+/// so every text widget handles editing keys and modifiers the same way.
+///
+/// Control characters (and characters typed with Ctrl or Cmd held, which are shortcuts) are filtered out,
+/// and key repeats produce repeated commands. Modifiers follow the platform conventions, e.g. word movement
+/// is Ctrl on Windows and Linux and Option on macOS. On Windows, AltGr is reported as Ctrl+Alt, so characters
+/// typed with Ctrl+Alt are inserted unless they're ASCII letters or digits, which layouts without AltGr type.
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    modifiers: HashMap<WindowId, ModifiersState>
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        TextInput::default()
+    }
+
+    /// Converts an event into a text command if it is one, and tracks modifiers
+    pub fn handle_event<T>(&mut self, event: &Event<T>) -> Option<TextInputEvent> {
+        let (window_id, event) = match event {
+            Event::WindowEvent { window_id, event } => (*window_id, event),
+            _ => return None
+        };
+        let command = match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers.insert(window_id, *modifiers);
+                return None
+            }
+            WindowEvent::Destroyed => {
+                self.modifiers.remove(&window_id);
+                return None
+            }
+            WindowEvent::ReceivedCharacter(char) => char_command(*char, self.modifiers(window_id), Platform::CURRENT)?,
+            // Synthetic presses are keys which were already held when the window was focused
+            WindowEvent::KeyboardInput { input, is_synthetic: false, .. } if input.state == ElementState::Pressed => {
+                key_command(input.virtual_keycode?, self.modifiers(window_id), Platform::CURRENT)?
+            }
+            _ => return None
+        };
+        Some(TextInputEvent { window_id, command })
+    }
+
+    fn modifiers(&self, window_id: WindowId) -> ModifiersState {
+        self.modifiers.get(&window_id).copied().unwrap_or_default()
+    }
+}
+
+/// Whose conventions to follow, a parameter so every platform's are tested
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    MacOs,
+    Windows,
+    /// Linux and others
+    Other
+}
+
+impl Platform {
+    const CURRENT: Platform = if cfg!(target_os = "macos") {
+        Platform::MacOs
+    } else if cfg!(target_os = "windows") {
+        Platform::Windows
+    } else {
+        Platform::Other
+    };
+}
+
+fn char_command(char: char, modifiers: ModifiersState, platform: Platform) -> Option<TextCommand> {
+    let is_shortcut = match platform {
+        Platform::MacOs => modifiers.logo() || modifiers.ctrl(),
+        // AltGr is reported as Ctrl+Alt, and types characters. Layouts without it type the key's own character
+        Platform::Windows if modifiers.ctrl() && modifiers.alt() => char.is_ascii_alphanumeric(),
+        Platform::Windows | Platform::Other => modifiers.ctrl()
+    };
+    if char.is_control() || is_function_key_char(char) || is_shortcut {
+        None
+    } else {
+        Some(TextCommand::Insert(char.to_string()))
+    }
+}
+
+fn key_command(key: VirtualKeyCode, modifiers: ModifiersState, platform: Platform) -> Option<TextCommand> {
+    use TextDirection::*;
+    let macos = platform == Platform::MacOs;
+    let primary = if macos { modifiers.logo() } else { modifiers.ctrl() };
+    let word = if macos { modifiers.alt() } else { modifiers.ctrl() };
+    let select = modifiers.shift();
+    let horizontal = |direction| if macos && primary {
+        TextMovement::LineBoundary(direction)
+    } else if word {
+        TextMovement::Word(direction)
+    } else {
+        TextMovement::Char(direction)
+    };
+    let vertical = |direction| if macos && primary {
+        TextMovement::Document(direction)
+    } else {
+        TextMovement::Line(direction)
+    };
+    let boundary = |direction| if primary {
+        TextMovement::Document(direction)
+    } else {
+        TextMovement::LineBoundary(direction)
+    };
+    Some(match key {
+        VirtualKeyCode::Back => TextCommand::Delete { movement: horizontal(Backward) },
+        VirtualKeyCode::Delete => TextCommand::Delete { movement: horizontal(Forward) },
+        VirtualKeyCode::Left => TextCommand::MoveCaret { movement: horizontal(Backward), select },
+        VirtualKeyCode::Right => TextCommand::MoveCaret { movement: horizontal(Forward), select },
+        VirtualKeyCode::Up => TextCommand::MoveCaret { movement: vertical(Backward), select },
+        VirtualKeyCode::Down => TextCommand::MoveCaret { movement: vertical(Forward), select },
+        VirtualKeyCode::Home => TextCommand::MoveCaret { movement: boundary(Backward), select },
+        VirtualKeyCode::End => TextCommand::MoveCaret { movement: boundary(Forward), select },
+        VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => TextCommand::Newline,
+        VirtualKeyCode::Tab if !(modifiers.ctrl() || modifiers.alt() || modifiers.logo()) => {
+            TextCommand::Tab(if select { Backward } else { Forward })
+        }
+        VirtualKeyCode::A if primary && !select => TextCommand::SelectAll,
+        _ => return None
+    })
+}
+
+/// macOS sends arrow and function keys as characters in this private use range
+fn is_function_key_char(char: char) -> bool {
+    ('\u{f700}'..='\u{f8ff}').contains(&char)
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{ModifiersState, VirtualKeyCode};
+    use super::{char_command, key_command, Platform, TextCommand, TextDirection::*, TextMovement};
+
+    const NONE: ModifiersState = ModifiersState::empty();
+    const CTRL: ModifiersState = ModifiersState::CTRL;
+    const ALT: ModifiersState = ModifiersState::ALT;
+    const LOGO: ModifiersState = ModifiersState::LOGO;
+    const SHIFT: ModifiersState = ModifiersState::SHIFT;
+
+    fn movement(key: VirtualKeyCode, modifiers: ModifiersState, platform: Platform) -> Option<TextMovement> {
+        match key_command(key, modifiers, platform)? {
+            TextCommand::MoveCaret { movement, .. } | TextCommand::Delete { movement } => Some(movement),
+            command => panic!("{:?} isn't a movement", command)
+        }
+    }
+
+    #[test]
+    fn windows_and_linux_movements() {
+        for platform in [Platform::Windows, Platform::Other] {
+            let movement = |key, modifiers| movement(key, modifiers, platform);
+            assert_eq!(movement(VirtualKeyCode::Left, NONE), Some(TextMovement::Char(Backward)));
+            assert_eq!(movement(VirtualKeyCode::Right, CTRL), Some(TextMovement::Word(Forward)));
+            assert_eq!(movement(VirtualKeyCode::Back, CTRL), Some(TextMovement::Word(Backward)));
+            assert_eq!(movement(VirtualKeyCode::Delete, NONE), Some(TextMovement::Char(Forward)));
+            assert_eq!(movement(VirtualKeyCode::Home, NONE), Some(TextMovement::LineBoundary(Backward)));
+            assert_eq!(movement(VirtualKeyCode::End, CTRL), Some(TextMovement::Document(Forward)));
+            assert_eq!(movement(VirtualKeyCode::Up, NONE), Some(TextMovement::Line(Backward)));
+            // Alt isn't word movement here
+            assert_eq!(movement(VirtualKeyCode::Left, ALT), Some(TextMovement::Char(Backward)));
+            assert_eq!(key_command(VirtualKeyCode::A, CTRL, platform), Some(TextCommand::SelectAll));
+            assert_eq!(key_command(VirtualKeyCode::A, LOGO, platform), None);
+        }
+    }
+
+    #[test]
+    fn macos_movements() {
+        let movement = |key, modifiers| movement(key, modifiers, Platform::MacOs);
+        assert_eq!(movement(VirtualKeyCode::Left, NONE), Some(TextMovement::Char(Backward)));
+        assert_eq!(movement(VirtualKeyCode::Right, ALT), Some(TextMovement::Word(Forward)));
+        assert_eq!(movement(VirtualKeyCode::Back, ALT), Some(TextMovement::Word(Backward)));
+        assert_eq!(movement(VirtualKeyCode::Left, LOGO), Some(TextMovement::LineBoundary(Backward)));
+        assert_eq!(movement(VirtualKeyCode::Back, LOGO), Some(TextMovement::LineBoundary(Backward)));
+        assert_eq!(movement(VirtualKeyCode::Down, LOGO), Some(TextMovement::Document(Forward)));
+        assert_eq!(movement(VirtualKeyCode::Home, NONE), Some(TextMovement::LineBoundary(Backward)));
+        // Ctrl isn't word movement here
+        assert_eq!(movement(VirtualKeyCode::Right, CTRL), Some(TextMovement::Char(Forward)));
+        assert_eq!(key_command(VirtualKeyCode::A, LOGO, Platform::MacOs), Some(TextCommand::SelectAll));
+        assert_eq!(key_command(VirtualKeyCode::A, CTRL, Platform::MacOs), None);
+    }
+
+    #[test]
+    fn selects_with_shift() {
+        assert_eq!(
+            key_command(VirtualKeyCode::Left, CTRL | SHIFT, Platform::Other),
+            Some(TextCommand::MoveCaret { movement: TextMovement::Word(Backward), select: true })
+        );
+        assert_eq!(key_command(VirtualKeyCode::A, CTRL | SHIFT, Platform::Other), None);
+    }
+
+    #[test]
+    fn tab() {
+        for platform in [Platform::MacOs, Platform::Windows, Platform::Other] {
+            assert_eq!(key_command(VirtualKeyCode::Tab, NONE, platform), Some(TextCommand::Tab(Forward)));
+            assert_eq!(key_command(VirtualKeyCode::Tab, SHIFT, platform), Some(TextCommand::Tab(Backward)));
+            assert_eq!(key_command(VirtualKeyCode::Tab, CTRL, platform), None);
+            assert_eq!(key_command(VirtualKeyCode::Tab, ALT, platform), None);
+            // The tab character itself isn't inserted
+            assert_eq!(char_command('\t', NONE, platform), None);
+        }
+    }
+
+    #[test]
+    fn filters_shortcut_characters() {
+        let insert = |char: char| Some(TextCommand::Insert(char.to_string()));
+        assert_eq!(char_command('a', SHIFT, Platform::Other), insert('a'));
+        assert_eq!(char_command('a', CTRL, Platform::Other), None);
+        assert_eq!(char_command('a', CTRL | ALT, Platform::Other), None);
+        assert_eq!(char_command('a', LOGO, Platform::MacOs), None);
+        assert_eq!(char_command('å', ALT, Platform::MacOs), insert('å'));
+        assert_eq!(char_command('\u{f702}', NONE, Platform::MacOs), None);
+        // AltGr on Windows
+        assert_eq!(char_command('@', CTRL | ALT, Platform::Windows), insert('@'));
+        assert_eq!(char_command('€', CTRL | ALT, Platform::Windows), insert('€'));
+        // Ctrl+Alt on layouts without AltGr
+        assert_eq!(char_command('a', CTRL | ALT, Platform::Windows), None);
+        assert_eq!(char_command('1', CTRL | ALT, Platform::Windows), None);
+        assert_eq!(char_command('a', CTRL, Platform::Windows), None);
+    }
+}
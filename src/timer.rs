@@ -0,0 +1,162 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+use crossbeam_utils::atomic::AtomicCell;
+use futures::Stream;
+use crate::messages::{main_proxy, MainEvent};
+
+/// Future which completes at a deadline. The main event loop wakes it, so it's precise even if no
+/// OS events arrive, and there's no separate timer thread.
+///
+/// Get one from [sleep], [sleep_until] or [EventLoop::sleep](crate::event_loop::EventLoop::sleep).
+#[must_use = "the timer won't actually start until you await or poll"]
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: Instant,
+    shared: Option<Arc<TimerShared>>
+}
+
+/// Stream which yields at a fixed period, woken by the main event loop like [Sleep].
+/// Each item is the deadline of the tick. If ticks are missed they are skipped.
+///
+/// Get one from [interval] or [EventLoop::interval](crate::event_loop::EventLoop::interval).
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Interval {
+    period: Duration,
+    sleep: Sleep
+}
+
+/// Shared between a [Sleep] and the main loop
+pub(crate) struct TimerShared {
+    fired: AtomicBool,
+    waker: AtomicCell<Option<Waker>>
+}
+
+/// A pending timer in the main loop
+pub(crate) struct MainTimer {
+    pub(crate) deadline: Instant,
+    pub(crate) shared: Weak<TimerShared>
+}
+
+/// Waits for `duration`. You must call [winit_modular::run](crate::run) before this.
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now() + duration)
+}
+
+/// Waits until `deadline`. You must call [winit_modular::run](crate::run) before this.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        shared: None
+    }
+}
+
+/// Yields every `period`, starting one `period` from now. You must call [winit_modular::run](crate::run) before this.
+pub fn interval(period: Duration) -> Interval {
+    assert!(period > Duration::ZERO, "interval period must be non-zero");
+    Interval {
+        period,
+        sleep: sleep(period)
+    }
+}
+
+impl Sleep {
+    /// When this completes
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let deadline = self.deadline;
+        let shared = self.shared.get_or_insert_with(|| {
+            let shared = Arc::new(TimerShared {
+                fired: AtomicBool::new(false),
+                waker: AtomicCell::new(None)
+            });
+            // Also wakes the main loop so it recomputes when to wait until
+            let _ = main_proxy().send_event(MainEvent::AddTimer(MainTimer {
+                deadline,
+                shared: Arc::downgrade(&shared)
+            }));
+            shared
+        });
+        shared.waker.store(Some(cx.waker().clone()));
+        // Check again in case we fired before storing the waker
+        if shared.fired.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Interval {
+    /// The time between ticks
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+impl Stream for Interval {
+    type Item = Instant;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(()) => {
+                let tick = self.sleep.deadline;
+                let now = Instant::now();
+                let mut next = tick + self.period;
+                while next <= now {
+                    next += self.period;
+                }
+                self.sleep = sleep_until(next);
+                Poll::Ready(Some(tick))
+            }
+        }
+    }
+}
+
+impl MainTimer {
+    /// Fires if the deadline passed. Returns whether the timer is done, either fired or dropped
+    pub(crate) fn fire_if_due(&self, now: Instant) -> bool {
+        match self.shared.upgrade() {
+            None => true,
+            Some(_) if self.deadline > now => false,
+            Some(shared) => {
+                shared.fired.store(true, Ordering::Release);
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+                true
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for TimerShared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimerShared")
+            .field("fired", &self.fired.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Debug for MainTimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MainTimer")
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::window::{Theme, Window, WindowId};
+use crate::event::{Event, WindowEvent};
+
+/// What's known about a window from its [WindowEvent]s.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WindowState {
+    /// Inner size, from [WindowEvent::Resized] and [WindowEvent::ScaleFactorChanged]
+    pub size: PhysicalSize<u32>,
+    /// Outer position, from [WindowEvent::Moved], or `None` if unknown or unsupported on the platform
+    pub position: Option<PhysicalPosition<i32>>,
+    /// From [WindowEvent::Focused]
+    pub focused: bool,
+    /// From window creation and [WindowEvent::ScaleFactorChanged], or `None` if neither was seen,
+    /// e.g. for windows which weren't created via [EventLoop::create_window](crate::event_loop::EventLoop::create_window)
+    pub scale_factor: Option<f64>,
+    /// From [WindowEvent::ThemeChanged], or `None` if it never changed
+    pub theme: Option<Theme>,
+    /// Whether the cursor is inside the window, from [WindowEvent::CursorEntered] and [WindowEvent::CursorLeft]
+    pub cursor_inside: bool,
+    /// Latest cursor position relative to the window, from [WindowEvent::CursorMoved]
+    pub cursor_position: Option<PhysicalPosition<f64>>
+}
+
+/// The [WindowState] of every window, updated from events via [WindowStates::handle_event].
+///
+/// Proxies can maintain one automatically, see [EventLoop::track_window_states](crate::event_loop::EventLoop::track_window_states).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WindowStates {
+    windows: HashMap<WindowId, WindowState>
+}
+
+/// User event with the state of every window, sent by the main loop to proxies which ask for it via
+/// [FutEventLoop::with_snapshot](crate::future::FutEventLoop::with_snapshot).
+/// Also the response to [EventLoop::snapshot](crate::event_loop::EventLoop::snapshot).
+///
+/// It reflects every event before it, so later events are newer than the snapshot.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WindowSnapshot {
+    pub windows: HashMap<WindowId, WindowState>
+}
+
+impl WindowStates {
+    /// No windows
+    pub fn new() -> Self {
+        WindowStates::default()
+    }
+
+    /// Updates the window the event is for. Windows are added on their first event and removed on
+    /// [WindowEvent::Destroyed]. Windows which haven't had events yet start from [WindowState::default],
+    /// so it's more accurate to start from a [WindowSnapshot].
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        if let Event::WindowEvent { window_id, event } = event {
+            match event {
+                WindowEvent::Destroyed => drop(self.windows.remove(window_id)),
+                event => self.windows.entry(*window_id).or_default().apply(event)
+            }
+        }
+    }
+
+    /// Replaces the state of every window in the snapshot
+    pub fn apply_snapshot(&mut self, snapshot: &WindowSnapshot) {
+        self.windows.extend(snapshot.windows.iter().map(|(window_id, state)| (*window_id, *state)));
+    }
+
+    pub fn get(&self, window_id: WindowId) -> Option<&WindowState> {
+        self.windows.get(&window_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(WindowId, &WindowState)> + '_ {
+        self.windows.iter().map(|(window_id, state)| (*window_id, state))
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Adds a window which was just created
+    pub(crate) fn insert(&mut self, window_id: WindowId, state: WindowState) {
+        self.windows.insert(window_id, state);
+    }
+
+    pub(crate) fn snapshot(&self) -> WindowSnapshot {
+        WindowSnapshot { windows: self.windows.clone() }
+    }
+}
+
+impl From<WindowSnapshot> for WindowStates {
+    fn from(snapshot: WindowSnapshot) -> Self {
+        WindowStates { windows: snapshot.windows }
+    }
+}
+
+impl WindowState {
+    /// The state of a window which was just created
+    pub(crate) fn from_window(window: &Window) -> Self {
+        WindowState {
+            size: window.inner_size(),
+            position: window.outer_position().ok(),
+            scale_factor: Some(window.scale_factor()),
+            ..WindowState::default()
+        }
+    }
+
+    /// Updates the state from an event for this window
+    pub(crate) fn apply(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::Resized(size) => self.size = *size,
+            WindowEvent::Moved(position) => self.position = Some(*position),
+            WindowEvent::Focused(focused) => self.focused = *focused,
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                self.scale_factor = Some(*scale_factor);
+                if let Ok(new_inner_size) = new_inner_size.lock() {
+                    self.size = *new_inner_size;
+                }
+            }
+            WindowEvent::ThemeChanged(theme) => self.theme = Some(*theme),
+            WindowEvent::CursorEntered { .. } => self.cursor_inside = true,
+            WindowEvent::CursorLeft { .. } => self.cursor_inside = false,
+            WindowEvent::CursorMoved { position, .. } => self.cursor_position = Some(*position),
+            _ => {}
+        }
+    }
+}